@@ -1,19 +1,46 @@
 use clap::{Arg, ArgAction, ArgMatches, ColorChoice, Command};
-use rinex::prelude::{Constellation, Duration, Observable, TimeScale};
+use rinex::{
+    hardware::Antenna,
+    marker::{GeodeticMarker, MarkerType},
+    prelude::{Constellation, Duration, Observable, TimeScale},
+};
 
-use crate::{collecter::settings::Settings as RinexSettings, UbloxSettings};
+use crate::{
+    collecter::{
+        phase_shift::PhaseShiftMode, pvt::PvtFormat, qc::QcFormat,
+        settings::Settings as RinexSettings, skyplot::SkyplotFormat,
+    },
+    station::Station,
+    ubx::DynamicModel,
+    utils::{parse_time_reference, to_timescale},
+    UbloxSettings,
+};
 
-use std::{collections::HashMap, str::FromStr};
+use ublox::{AlignmentToReferenceTime, CfgLayerSet};
+
+use std::{collections::HashMap, ffi::OsString, str::FromStr};
 
 pub struct Cli {
     /// Arguments passed by user
     matches: ArgMatches,
+    /// [Station] profile, possibly loaded
+    station: Station,
 }
 
 impl Cli {
     /// Build new command line interface
     pub fn new() -> Self {
-        Self {
+        Self::from_args(std::env::args_os())
+    }
+
+    /// Builds [Cli] from these command line arguments
+    fn from_args<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let mut cli = Self {
+            station: Station::default(),
             matches: {
                 Command::new("ubx2rinex")
                     .author("Guillaume W. Bres, <guillaume.bressaix@gmail.com>")
@@ -44,35 +71,35 @@ impl Cli {
                             .long("gps")
                             .action(ArgAction::SetTrue)
                             .help("Activate GPS constellation")
-                            .required_unless_present_any(["galileo", "beidou", "qzss", "glonass"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "galileo", "beidou", "qzss", "glonass"]),
                     )
                     .arg(
                         Arg::new("galileo")
                             .long("galileo")
                             .action(ArgAction::SetTrue)
                             .help("Activate Galileo constellation")
-                            .required_unless_present_any(["gps", "beidou", "qzss", "glonass"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "gps", "beidou", "qzss", "glonass"]),
                     )
                     .arg(
                         Arg::new("bds")
                             .long("bds")
                             .action(ArgAction::SetTrue)
                             .help("Activate BDS (BeiDou) constellation")
-                            .required_unless_present_any(["galileo", "gps", "qzss", "glonass"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "galileo", "gps", "qzss", "glonass"]),
                     )
                     .arg(
                         Arg::new("qzss")
                             .long("qzss")
                             .action(ArgAction::SetTrue)
                             .help("Activate QZSS constellation")
-                            .required_unless_present_any(["galileo", "gps", "bds", "glonass"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "galileo", "gps", "bds", "glonass"]),
                     )
                     .arg(
                         Arg::new("glonass")
                             .long("glonass")
                             .action(ArgAction::SetTrue)
                             .help("Activate Glonass constellation")
-                            .required_unless_present_any(["galileo", "gps", "bds", "qzss"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "galileo", "gps", "bds", "qzss"]),
                     )
                     .next_help_heading("Signal selection - at least one required!")
                     .arg(
@@ -80,28 +107,47 @@ impl Cli {
                             .long("l1")
                             .action(ArgAction::SetTrue)
                             .help("Activate L1 signal for all constellations")
-                            .required_unless_present_any(["l2", "l5"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "l2", "l5"]),
                     )
                     .arg(
                         Arg::new("l2")
                             .long("l2")
                             .action(ArgAction::SetTrue)
                             .help("Activate L2 signal for all constellations")
-                            .required_unless_present_any(["l1", "l5"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "l1", "l5"]),
                     )
                     .arg(
                         Arg::new("l5")
                             .long("l5")
                             .action(ArgAction::SetTrue)
                             .help("Activate L5 signal for all constellations. Requires F9 or F10 series.")
-                            .required_unless_present_any(["l1", "l2"]),
+                            .required_unless_present_any(["dump-config", "reset-config", "l1", "l2"]),
                     )
                     .next_help_heading("U-Blox configuration")
                     .arg(
                         Arg::new("profile")
                             .long("prof")
+                            .value_name("PROFILE")
+                            .action(ArgAction::Set)
+                            .help("Define user profile (dynamic platform model): portable, stationary, pedestrian,
+automotive, sea, airborne<1g>, airborne<2g>, airborne<4g>, wrist or bike.
+Receiver settings are preserved by default (usually \"portable\")."),
+                    )
+                    .arg(
+                        Arg::new("min-elev")
+                            .long("min-elev")
+                            .value_name("DEGREES")
                             .action(ArgAction::Set)
-                            .help("Define user profile. Default is set to \"portable\""),
+                            .help("Navigation elevation mask: satellites below this elevation are not used
+in the navigation solution. This does not affect the collected observations."),
+                    )
+                    .arg(
+                        Arg::new("min-cno")
+                            .long("min-cno")
+                            .value_name("DBHZ")
+                            .action(ArgAction::Set)
+                            .help("Navigation C/N0 mask: satellites below this signal strength are not used
+in the navigation solution."),
                     )
                     .arg(
                         Arg::new("rx-clock")
@@ -109,11 +155,79 @@ impl Cli {
                             .action(ArgAction::SetTrue)
                             .help("Resolve clock state and capture it. Disabled by default"),
                     )
+                    .arg(
+                        Arg::new("rx-clock-apply")
+                            .long("rx-clock-apply")
+                            .action(ArgAction::SetTrue)
+                            .help("Resolve clock state and remove the receiver clock offset from the epochs and observations
+(RCV CLOCK OFFS APPL). Without this option, --rx-clock only reports the offset in each epoch."),
+                    )
+                    .arg(
+                        Arg::new("timemark")
+                            .long("timemark")
+                            .action(ArgAction::SetTrue)
+                            .help("Capture the time marks (EXTINT pin edges, UBX-TIM-TM2) and declare them
+as external events (epoch flag 5) in the Observation RINEX. Disabled by default."),
+                    )
+                    .arg(
+                        Arg::new("event-log")
+                            .long("event-log")
+                            .value_name("FILE")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Export the time marks to this CSV file as well. Implies --timemark."),
+                    )
                     .arg(
                         Arg::new("anti-spoofing")
                             .long("anti-spoofing")
                             .action(ArgAction::SetTrue)
-                            .help("Makes sure anti jamming/spoofing is enabled. When enabled, it is automatically emphasized in the collected RINEX."))
+                            .help("Makes sure anti jamming/spoofing is enabled (interference monitor, MON-HW, MON-RF, SEC-SIG).
+When enabled, jamming and spoofing detections are declared as events in the collected RINEX."))
+                    .arg(
+                        Arg::new("jamming-threshold")
+                            .long("jamming-threshold")
+                            .value_name("0..255")
+                            .action(ArgAction::Set)
+                            .requires("anti-spoofing")
+                            .help("CW jamming indicator threshold, above which jamming is declared. Default is 100."),
+                    )
+                    .arg(
+                        Arg::new("monitor-log")
+                            .long("monitor-log")
+                            .value_name("FILE")
+                            .action(ArgAction::Set)
+                            .requires("anti-spoofing")
+                            .help("Export the jamming/spoofing monitoring (jamming indicator, AGC, noise level,
+jamming and spoofing states) to this CSV file."),
+                    )
+                    .arg(
+                        Arg::new("strict-config")
+                            .long("strict-config")
+                            .action(ArgAction::SetTrue)
+                            .help("Abort when the configuration read back from the receiver (CFG-VALGET)
+does not match the requested configuration. Mismatches are only reported by default."),
+                    )
+                    .arg(
+                        Arg::new("dump-config")
+                            .long("dump-config")
+                            .action(ArgAction::SetTrue)
+                            .help("Print the current receiver configuration and exit."),
+                    )
+                    .arg(
+                        Arg::new("save-config")
+                            .long("save-config")
+                            .value_name("bbr,flash")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Save the receiver configuration to these non volatile layers
+(comma separated), so the receiver restarts in the same mode after power loss."),
+                    )
+                    .arg(
+                        Arg::new("reset-config")
+                            .long("reset-config")
+                            .action(ArgAction::SetTrue)
+                            .help("Restore the receiver default configuration (clears BBR and Flash), reset the receiver and exit."),
+                    )
                     .arg(
                         Arg::new("model")
                             .short('m')
@@ -123,6 +237,16 @@ impl Cli {
                             .help("Define u-Blox receiver model. For example \"u-Blox M8T\"")
                     )
                     .next_help_heading("RINEX Collection")
+                    .arg(
+                        Arg::new("station")
+                            .long("station")
+                            .value_name("FILE")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Load station profile (TOML or JSON file) describing the marker, antenna, receiver,
+agency, observer and country. Command line options prevail over the file content.
+See docs/station.toml.")
+                    )
                     .arg(
                         Arg::new("name")
                             .long("name")
@@ -194,6 +318,42 @@ We use V3 by default, because very few tools support V4, so we remain compatible
                             .required(false)
                             .help("Define name of Operator, to be used in all Headers"),
                     )
+                    .next_help_heading("Antenna description")
+                    .arg(
+                        Arg::new("ant-model")
+                            .long("ant-model")
+                            .action(ArgAction::Set)
+                            .required(false)
+                            .help("Define antenna model, to be used in Observation Headers"),
+                    )
+                    .arg(
+                        Arg::new("ant-sn")
+                            .long("ant-sn")
+                            .action(ArgAction::Set)
+                            .required(false)
+                            .help("Define antenna serial number, to be used in Observation Headers"),
+                    )
+                    .arg(
+                        Arg::new("ant-h")
+                            .long("ant-h")
+                            .action(ArgAction::Set)
+                            .required(false)
+                            .help("Antenna height (ARP) above marker, in meters. Default is 0."),
+                    )
+                    .arg(
+                        Arg::new("ant-e")
+                            .long("ant-e")
+                            .action(ArgAction::Set)
+                            .required(false)
+                            .help("Antenna eccentricity (East component) to marker, in meters. Default is 0."),
+                    )
+                    .arg(
+                        Arg::new("ant-n")
+                            .long("ant-n")
+                            .action(ArgAction::Set)
+                            .required(false)
+                            .help("Antenna eccentricity (North component) to marker, in meters. Default is 0."),
+                    )
                     .next_help_heading("Observations collection (signal sampling)")
                     .arg(
                         Arg::new("no-obs")
@@ -206,7 +366,15 @@ We use V3 by default, because very few tools support V4, so we remain compatible
                             .short('s')
                             .long("sampling")
                             .required(false)
-                            .help("Define sampling interval. Default value is 30s (standard low-rate RINEX).")
+                            .help("Define sampling interval. Default value is 30s (standard low-rate RINEX).
+Epochs that are not aligned to this interval are dropped.")
+                    )
+                    .arg(
+                        Arg::new("rate")
+                            .long("rate")
+                            .required(false)
+                            .help("Define the receiver measurement period, which may be shorter than the sampling interval.
+Default value is the sampling interval.")
                     )
                     .arg(
                         Arg::new("no-phase")
@@ -230,9 +398,78 @@ We use V3 by default, because very few tools support V4, so we remain compatible
                         Arg::new("timescale")
                             .long("timescale")
                             .required(false)
-                            .help("Express your observations in given Timescale.
+                            .help("Express your observations in given Timescale: GPST, GST, BDT, UTC or GLONASST.
 Default value is GPST."
                     ))
+                    .arg(
+                        Arg::new("remove-clock-jumps")
+                            .long("remove-clock-jumps")
+                            .action(ArgAction::SetTrue)
+                            .help("Remove the millisecond jumps of the receiver clock from the pseudo range and phase observations,
+so they remain continuous. Clock jumps and resets are always declared with event records.")
+                    )
+                    .arg(
+                        Arg::new("elev-mask")
+                            .long("elev-mask")
+                            .value_name("DEGREES")
+                            .action(ArgAction::Set)
+                            .help("Remove the observations of satellites below this elevation (NAV-SAT),
+for example 5 or 10 degrees. This is independent of the navigation mask (--min-elev).")
+                    )
+                    .arg(
+                        Arg::new("qc")
+                            .long("qc")
+                            .value_name("txt,json")
+                            .action(ArgAction::Set)
+                            .help("Produce a quality control report for each Observation RINEX file, in these formats
+(comma separated): completeness per SV and signal, data gaps, cycle slips, mean C/N0 versus elevation
+and multipath (MP1/MP2) estimates. The report is written next to the RINEX file.")
+                    )
+                    .arg(
+                        Arg::new("phase-shift")
+                            .long("phase-shift")
+                            .value_name("apply|declare")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Handle the quarter-cycle phase shifts of each signal, so phase data is consistent
+with other receivers. \"apply\" corrects the phase observations and declares it in the header (SYS / PHASE SHIFT).
+\"declare\" only declares the corrections in the header. Requires RINEX V3 or newer.")
+                    )
+                    .arg(
+                        Arg::new("learn-observables")
+                            .long("learn-observables")
+                            .action(ArgAction::SetTrue)
+                            .help("Build the header observables from the signals actually tracked during the learning period
+(see --learning), rather than from the signal selection. Avoids empty columns and missing signals.")
+                    )
+                    .arg(
+                        Arg::new("learning")
+                            .long("learning")
+                            .value_name("EPOCHS")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Number of epochs we gather before releasing the header, so it describes them completely
+(for example, the Glonass frequency channels). Default is 1, or 10 when Glonass is activated.")
+                    )
+                    .arg(
+                        Arg::new("ubx-archive")
+                            .long("ubx-archive")
+                            .action(ArgAction::SetTrue)
+                            .help("Archive the raw UBX stream as well, so it may be reprocessed later on.
+Files are rotated on the snapshot period, and compressed with --gzip.")
+                    )
+                    .arg(
+                        Arg::new("obs-product")
+                            .long("obs-product")
+                            .value_name("SPEC")
+                            .required(false)
+                            .action(ArgAction::Append)
+                            .help("Define one Observation RINEX product. Repeat this option to produce several products
+from the same stream. SPEC is a comma separated list that customizes the general options:
+v2|v3|v4, short|long, crx, gzip, period=DURATION, sampling=DURATION, name=NAME, prefix=DIR,
+elev-mask=DEGREES and gnss=LETTERS (constellation filter, for example gnss=GE).
+Example: --obs-product \"v3,long,period=1 hour,sampling=1 s\" --obs-product \"v2,sampling=30 s,gnss=G\"")
+                    )
                     .arg(
                         Arg::new("crx")
                             .long("crx")
@@ -244,6 +481,84 @@ Default value is GPST."
                             .long("gzip")
                             .action(ArgAction::SetTrue)
                             .help("Activate Gzip compression."))
+                    .next_help_heading("Clock RINEX collection")
+                    .arg(
+                        Arg::new("clk")
+                            .long("clk")
+                            .action(ArgAction::SetTrue)
+                            .help("Produce RINEX Clock files as well, with receiver clock (AR) records:
+clock bias and drift (UBX-NAV-CLOCK). Files are rotated on the snapshot period. Implies --rx-clock.")
+                    )
+                    .arg(
+                        Arg::new("clk-pps")
+                            .long("clk-pps")
+                            .action(ArgAction::SetTrue)
+                            .requires("clk")
+                            .help("Refer the RINEX Clock bias to the time pulse (PPS) output: the time pulse
+quantization error (UBX-TIM-TP) is added to the clock bias. This is declared in the header comments.")
+                    )
+                    .next_help_heading("Receiver position")
+                    .arg(
+                        Arg::new("survey")
+                            .long("survey")
+                            .value_name("ACCURACY")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Average the navigation solutions until the mean position reaches this accuracy (in meters).
+The surveyed position then describes the Observation RINEX (APPROX POSITION XYZ).")
+                    )
+                    .arg(
+                        Arg::new("survey-in")
+                            .long("survey-in")
+                            .action(ArgAction::SetTrue)
+                            .requires("survey")
+                            .help("Let the receiver run the survey (TMODE3 survey-in) instead of averaging the solutions ourselves.
+Requires F9 series (or newer), or an M8 timing receiver (TMODE2).")
+                    )
+                    .arg(
+                        Arg::new("survey-time")
+                            .long("survey-time")
+                            .value_name("DURATION")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Minimal duration of the receiver survey-in. Default is 5 minutes.")
+                    )
+                    .arg(
+                        Arg::new("fixed-mode")
+                            .long("fixed-mode")
+                            .action(ArgAction::SetTrue)
+                            .help("Push the surveyed (or known) position to the receiver, which then operates in fixed position timing mode.")
+                    )
+                    .arg(
+                        Arg::new("position-file")
+                            .long("position-file")
+                            .value_name("FILE")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Store the surveyed position in this JSON file. When this file exists,
+the position is known and we do not survey again.")
+                    )
+                    .next_help_heading("Receiver position log")
+                    .arg(
+                        Arg::new("pvt")
+                            .long("pvt")
+                            .value_name("csv,gga,gpx")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Log the navigation solutions (UBX-NAV-PVT) in these formats (comma separated):
+CSV, NMEA GGA sentences and/or GPX track. Files are rotated on the snapshot period.")
+                    )
+                    .next_help_heading("Satellite visibility")
+                    .arg(
+                        Arg::new("skyplot")
+                            .long("skyplot")
+                            .value_name("csv,json")
+                            .required(false)
+                            .action(ArgAction::Set)
+                            .help("Export the satellite states (UBX-NAV-SAT: elevation, azimuth, C/N0, residual,
+usage, health and ephemeris availability) in these formats (comma separated).
+Files are rotated on the snapshot period.")
+                    )
                     .next_help_heading("Navigation messages collection")
                             .arg(
                                 Arg::new("nav")
@@ -257,9 +572,15 @@ Default value is GPST."
                                     .long("gzip")
                                     .action(ArgAction::SetTrue)
                                     .help("Activate Gzip compression."))
-                    .get_matches()
+                    .get_matches_from(args)
             },
+        };
+
+        if let Some(path) = cli.matches.get_one::<String>("station") {
+            cli.station = Station::from_file(path);
         }
+
+        cli
     }
 
     /// Returns User serial port specification
@@ -267,6 +588,180 @@ Default value is GPST."
         self.matches.get_one::<String>("port").unwrap()
     }
 
+    /// Returns the desired [PvtFormat]s, if any
+    pub fn pvt_formats(&self) -> Vec<PvtFormat> {
+        match self.matches.get_one::<String>("pvt") {
+            Some(formats) => formats
+                .split(',')
+                .map(|format| {
+                    PvtFormat::from_str(format)
+                        .unwrap_or_else(|e| panic!("Invalid PVT format: {}", e))
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the desired [SkyplotFormat]s, if any
+    pub fn skyplot_formats(&self) -> Vec<SkyplotFormat> {
+        match self.matches.get_one::<String>("skyplot") {
+            Some(formats) => formats
+                .split(',')
+                .map(|format| {
+                    SkyplotFormat::from_str(format)
+                        .unwrap_or_else(|e| panic!("Invalid skyplot format: {}", e))
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the desired [QcFormat]s, if any
+    fn qc_formats(&self) -> Vec<QcFormat> {
+        match self.matches.get_one::<String>("qc") {
+            Some(formats) => formats
+                .split(',')
+                .map(|format| {
+                    QcFormat::from_str(format)
+                        .unwrap_or_else(|e| panic!("Invalid QC format: {}", e))
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns true if we should only print the receiver configuration
+    pub fn dump_config(&self) -> bool {
+        self.matches.get_flag("dump-config")
+    }
+
+    /// Returns true if we should only restore the receiver default configuration
+    pub fn reset_config(&self) -> bool {
+        self.matches.get_flag("reset-config")
+    }
+
+    /// Returns the dynamic model (user profile), if any
+    fn dyn_model(&self) -> Option<DynamicModel> {
+        let profile = self.matches.get_one::<String>("profile")?;
+
+        Some(DynamicModel::from_str(profile).unwrap_or_else(|e| panic!("Invalid profile: {}", e)))
+    }
+
+    /// Returns true if jamming and spoofing monitoring is requested
+    pub fn anti_spoofing(&self) -> bool {
+        self.matches.get_flag("anti-spoofing")
+    }
+
+    /// Returns CW jamming indicator threshold
+    pub fn jamming_threshold(&self) -> u8 {
+        match self.matches.get_one::<String>("jamming-threshold") {
+            Some(threshold) => threshold
+                .trim()
+                .parse::<u8>()
+                .unwrap_or_else(|e| panic!("Invalid jamming threshold: {}", e)),
+            None => 100,
+        }
+    }
+
+    /// Returns monitoring log file, if any
+    pub fn monitor_log(&self) -> Option<&String> {
+        self.matches.get_one::<String>("monitor-log")
+    }
+
+    /// Returns navigation elevation mask [°], if any
+    fn min_elev(&self) -> Option<i8> {
+        let min_elev = self.matches.get_one::<String>("min-elev")?;
+        let min_elev = min_elev
+            .trim()
+            .parse::<i8>()
+            .unwrap_or_else(|e| panic!("Invalid elevation mask: {}", e));
+
+        if !(0..=90).contains(&min_elev) {
+            panic!("Elevation mask should be within 0..90 degrees");
+        }
+
+        Some(min_elev)
+    }
+
+    /// Returns navigation C/N0 mask [dB.Hz], if any
+    fn min_cno(&self) -> Option<u8> {
+        let min_cno = self.matches.get_one::<String>("min-cno")?;
+        let min_cno = min_cno
+            .trim()
+            .parse::<u8>()
+            .unwrap_or_else(|e| panic!("Invalid C/N0 mask: {}", e));
+        Some(min_cno)
+    }
+
+    /// Returns non volatile layers the configuration should be saved to
+    fn save_layers(&self) -> CfgLayerSet {
+        let mut layers = CfgLayerSet::empty();
+
+        if let Some(save) = self.matches.get_one::<String>("save-config") {
+            for layer in save.split(',') {
+                match layer.trim().to_lowercase().as_str() {
+                    "bbr" => layers |= CfgLayerSet::BBR,
+                    "flash" => layers |= CfgLayerSet::FLASH,
+                    layer => panic!("Invalid configuration layer \"{}\" (bbr, flash)", layer),
+                }
+            }
+        }
+
+        layers
+    }
+
+    /// Returns true if RINEX Clock production is desired
+    pub fn clk(&self) -> bool {
+        self.matches.get_flag("clk")
+    }
+
+    /// Returns desired survey accuracy [m], if any
+    pub fn survey_accuracy(&self) -> Option<f64> {
+        let accuracy = self.matches.get_one::<String>("survey")?;
+        let accuracy = accuracy
+            .trim()
+            .parse::<f64>()
+            .unwrap_or_else(|e| panic!("Invalid survey accuracy: {}", e));
+        Some(accuracy)
+    }
+
+    /// Returns true if receiver survey-in is preferred
+    pub fn survey_in(&self) -> bool {
+        self.matches.get_flag("survey-in")
+    }
+
+    /// Returns minimal survey-in duration
+    pub fn survey_time(&self) -> Duration {
+        if let Some(duration) = self.matches.get_one::<String>("survey-time") {
+            duration
+                .trim()
+                .parse::<Duration>()
+                .unwrap_or_else(|e| panic!("Invalid duration: {}", e))
+        } else {
+            Duration::from_seconds(300.0)
+        }
+    }
+
+    /// Returns true if fixed position timing mode is desired
+    pub fn fixed_mode(&self) -> bool {
+        self.matches.get_flag("fixed-mode")
+    }
+
+    /// Returns position file, if any
+    pub fn position_file(&self) -> Option<&String> {
+        self.matches.get_one::<String>("position-file")
+    }
+
+    /// Returns true if raw UBX archiving is desired
+    pub fn ubx_archive(&self) -> bool {
+        self.matches.get_flag("ubx-archive")
+    }
+
+    /// Returns time marks CSV log, if desired
+    pub fn event_log(&self) -> Option<&String> {
+        self.matches.get_one::<String>("event-log")
+    }
+
     /// Returns User baud rate specification
     pub fn baud_rate(&self) -> Option<u32> {
         let baud = self.matches.get_one::<String>("baudrate")?;
@@ -341,107 +836,47 @@ Default value is GPST."
         self.matches.get_flag("no-phase")
     }
 
-    fn observables(&self) -> HashMap<Constellation, Vec<Observable>> {
-        let v2 = self.matches.get_flag("v2");
-        let mut ret = HashMap::new();
+    /// Signal code u-Blox produces, for given frequency band (1, 2 or 5)
+    fn signal_code(constellation: Constellation, band: u8) -> Option<&'static str> {
+        match (constellation, band) {
+            (Constellation::GPS | Constellation::QZSS, 1) => Some("1C"),
+            (Constellation::GPS | Constellation::QZSS, 2) => Some("2L"),
+            (Constellation::GPS | Constellation::QZSS, 5) => Some("5Q"),
+            (Constellation::Galileo, 1) => Some("1C"),
+            (Constellation::Galileo, 2) => Some("7Q"),
+            (Constellation::Galileo, 5) => Some("5Q"),
+            (Constellation::BeiDou, 1) => Some("2I"),
+            (Constellation::BeiDou, 2) => Some("7I"),
+            (Constellation::BeiDou, 5) => Some("5P"),
+            (Constellation::Glonass, 1) => Some("1C"),
+            (Constellation::Glonass, 2) => Some("2C"),
+            _ => None,
+        }
+    }
 
-        for constell in self.constellations().iter() {
-            if self.l1() {
-                let mut values = match constell {
-                    Constellation::GPS
-                    | Constellation::Glonass
-                    | Constellation::Galileo
-                    | Constellation::QZSS => {
-                        if v2 {
-                            vec![
-                                Observable::from_str("C1").unwrap(),
-                                Observable::from_str("D1").unwrap(),
-                                Observable::from_str("L1").unwrap(),
-                            ]
-                        } else {
-                            vec![
-                                Observable::from_str("C1C").unwrap(),
-                                Observable::from_str("D1C").unwrap(),
-                                Observable::from_str("L1C").unwrap(),
-                            ]
-                        }
-                    },
-                    _ => {
-                        vec![]
-                    },
-                };
+    fn observables(&self, v2: bool) -> HashMap<Constellation, Vec<Observable>> {
+        let mut ret = HashMap::<Constellation, Vec<Observable>>::new();
 
-                if self.no_dop() {
-                    values.retain(|code| !code.is_doppler_observable());
-                }
-                if self.no_phase() {
-                    values.retain(|code| !code.is_phase_range_observable());
-                }
-                if self.no_pr() {
-                    values.retain(|code| !code.is_pseudo_range_observable());
-                }
-                if !values.is_empty() {
-                    ret.insert(*constell, values);
+        let bands = [(1, self.l1()), (2, self.l2()), (5, self.l5())];
+
+        for constell in self.constellations().iter() {
+            for (band, enabled) in bands {
+                if !enabled {
+                    continue;
                 }
-            }
 
-            if self.l2() {
-                let mut values = match constell {
-                    Constellation::GPS | Constellation::Glonass | Constellation::QZSS => {
-                        if v2 {
-                            vec![
-                                Observable::from_str("C2").unwrap(),
-                                Observable::from_str("D2").unwrap(),
-                                Observable::from_str("L2").unwrap(),
-                            ]
-                        } else {
-                            vec![
-                                Observable::from_str("C2C").unwrap(),
-                                Observable::from_str("D2C").unwrap(),
-                                Observable::from_str("L2C").unwrap(),
-                            ]
-                        }
-                    },
-                    _ => {
-                        vec![]
-                    },
+                let code = match Self::signal_code(*constell, band) {
+                    Some(code) => code,
+                    None => continue,
                 };
 
-                if self.no_dop() {
-                    values.retain(|code| !code.is_doppler_observable());
-                }
-                if self.no_phase() {
-                    values.retain(|code| !code.is_phase_range_observable());
-                }
-                if self.no_pr() {
-                    values.retain(|code| !code.is_pseudo_range_observable());
-                }
-                if !values.is_empty() {
-                    ret.insert(*constell, values);
-                }
-            }
+                let code = if v2 { &code[..1] } else { code };
 
-            if self.l5() {
-                let mut values = match constell {
-                    Constellation::GPS | Constellation::Galileo | Constellation::QZSS => {
-                        if v2 {
-                            vec![
-                                Observable::from_str("C5").unwrap(),
-                                Observable::from_str("D5").unwrap(),
-                                Observable::from_str("L5").unwrap(),
-                            ]
-                        } else {
-                            vec![
-                                Observable::from_str("C5C").unwrap(),
-                                Observable::from_str("D5C").unwrap(),
-                                Observable::from_str("L5C").unwrap(),
-                            ]
-                        }
-                    },
-                    _ => {
-                        vec![]
-                    },
-                };
+                let mut values = vec![
+                    Observable::from_str(&format!("C{}", code)).unwrap(),
+                    Observable::from_str(&format!("D{}", code)).unwrap(),
+                    Observable::from_str(&format!("L{}", code)).unwrap(),
+                ];
 
                 if self.no_dop() {
                     values.retain(|code| !code.is_doppler_observable());
@@ -452,24 +887,125 @@ Default value is GPST."
                 if self.no_pr() {
                     values.retain(|code| !code.is_pseudo_range_observable());
                 }
-                if !values.is_empty() {
-                    ret.insert(*constell, values);
+
+                let entry = ret.entry(*constell).or_default();
+
+                for value in values {
+                    if !entry.contains(&value) {
+                        entry.push(value);
+                    }
                 }
             }
         }
         ret
     }
 
-    fn timescale(&self) -> TimeScale {
+    fn antenna_offset(&self, key: &str, station: Option<f64>) -> f64 {
+        if let Some(offset) = self.matches.get_one::<String>(key) {
+            offset
+                .trim()
+                .parse::<f64>()
+                .unwrap_or_else(|e| panic!("Invalid antenna offset: {}", e))
+        } else {
+            station.unwrap_or_default()
+        }
+    }
+
+    fn antenna(&self) -> Option<Antenna> {
+        let station = self.station.antenna.clone().unwrap_or_default();
+
+        let model = match self.matches.get_one::<String>("ant-model") {
+            Some(model) => model.to_string(),
+            None => self.station.antenna.as_ref()?.model.clone(),
+        };
+
+        let mut antenna = Antenna::default()
+            .with_model(&model)
+            .with_height(self.antenna_offset("ant-h", station.height))
+            .with_eastern_component(self.antenna_offset("ant-e", station.eastern))
+            .with_northern_component(self.antenna_offset("ant-n", station.northern));
+
+        if let Some(sn) = self
+            .matches
+            .get_one::<String>("ant-sn")
+            .or(station.sn.as_ref())
+        {
+            antenna = antenna.with_serial_number(sn);
+        }
+
+        Some(antenna)
+    }
+
+    fn marker(&self) -> Option<GeodeticMarker> {
+        let station = self.station.marker.as_ref()?;
+
+        let mut marker = GeodeticMarker::default();
+
+        if let Some(name) = &station.name {
+            marker = marker.with_name(name);
+        }
+
+        if let Some(number) = &station.number {
+            marker = marker.with_number(number);
+        }
+
+        if let Some(marker_type) = &station.marker_type {
+            let marker_type = MarkerType::from_str(marker_type.trim())
+                .unwrap_or_else(|e| panic!("Invalid marker type: {}", e));
+
+            marker.marker_type = Some(marker_type);
+        }
+
+        Some(marker)
+    }
+
+    /// Returns user option, or station definition
+    fn station_opt(&self, key: &str, station: &Option<String>) -> Option<String> {
+        if let Some(value) = self.matches.get_one::<String>(key) {
+            Some(value.to_string())
+        } else {
+            station.clone()
+        }
+    }
+
+    fn learning_epochs(&self) -> usize {
+        if let Some(epochs) = self.matches.get_one::<String>("learning") {
+            epochs
+                .trim()
+                .parse::<usize>()
+                .unwrap_or_else(|e| panic!("Invalid number of epochs: {}", e))
+                .max(1)
+        } else if self.glonass() {
+            10
+        } else {
+            1
+        }
+    }
+
+    fn phase_shift(&self) -> Option<PhaseShiftMode> {
+        let mode = self.matches.get_one::<String>("phase-shift")?;
+        let mode = PhaseShiftMode::from_str(mode)
+            .unwrap_or_else(|e| panic!("Invalid phase shift mode: {}", e));
+        Some(mode)
+    }
+
+    fn time_reference(&self) -> AlignmentToReferenceTime {
         if let Some(ts) = self.matches.get_one::<String>("timescale") {
-            let ts = TimeScale::from_str(ts.trim())
-                .unwrap_or_else(|e| panic!("Invalid timescale: {}", e));
-            ts
+            parse_time_reference(ts).unwrap_or_else(|e| panic!("Invalid timescale: {}", e))
         } else {
-            TimeScale::GPST
+            AlignmentToReferenceTime::Gps
         }
     }
 
+    fn timescale(&self) -> TimeScale {
+        to_timescale(self.time_reference())
+    }
+
+    /// GLONASST is expressed as UTC, but declared as GLO
+    fn glonass_time(&self) -> bool {
+        matches!(self.time_reference(), AlignmentToReferenceTime::Glo)
+    }
+
     fn sampling_period(&self) -> Duration {
         if let Some(sampling) = self.matches.get_one::<String>("sampling") {
             let dt = sampling
@@ -486,8 +1022,29 @@ Default value is GPST."
         }
     }
 
-    fn solutions_ratio(sampling_period: Duration) -> u16 {
-        let period_ms = (sampling_period.total_nanoseconds() / 1_000_000) as u16;
+    /// Receiver measurement period
+    fn measurement_period(&self) -> Duration {
+        if let Some(rate) = self.matches.get_one::<String>("rate") {
+            let dt = rate
+                .trim()
+                .parse::<Duration>()
+                .unwrap_or_else(|e| panic!("Invalid duration: {}", e));
+
+            if dt.total_nanoseconds() < 50_000_000 {
+                panic!("Measurement period is limited to 50ms");
+            }
+            dt
+        } else {
+            self.rinex_products()
+                .iter()
+                .map(|product| product.sampling_period)
+                .min()
+                .unwrap_or(self.sampling_period())
+        }
+    }
+
+    fn solutions_ratio(measurement_period: Duration) -> u16 {
+        let period_ms = (measurement_period.total_nanoseconds() / 1_000_000) as u16;
         if period_ms > 10_000 {
             1
         } else if period_ms > 1_000 {
@@ -498,25 +1055,40 @@ Default value is GPST."
     }
 
     pub fn ublox_settings(&self) -> UbloxSettings {
-        let sampling_period = self.sampling_period();
+        let measurement_period = self.measurement_period();
         UbloxSettings {
             l1: self.l1(),
             l2: self.l2(),
             l5: self.l5(),
-            sampling_period,
+            measurement_period,
             rawxm: !self.matches.get_flag("no-obs"),
             ephemeris: self.matches.get_flag("nav"),
             timescale: self.timescale(),
+            time_ref: self.time_reference(),
             constellations: self.constellations(),
-            rx_clock: self.matches.get_flag("rx-clock"),
-            solutions_ratio: Self::solutions_ratio(sampling_period),
+            rx_clock: self.matches.get_flag("rx-clock")
+                || self.matches.get_flag("rx-clock-apply")
+                || self.clk(),
+            timepulse: self.clk(),
+            timemark: self.matches.get_flag("timemark") || self.event_log().is_some(),
+            solutions_ratio: Self::solutions_ratio(measurement_period),
             sn: None,
             firmware: None,
-            model: if let Some(model) = self.matches.get_one::<String>("model") {
-                Some(model.to_string())
-            } else {
-                None
-            },
+            legacy_cfg: false,
+            strict_cfg: self.matches.get_flag("strict-config"),
+            save_layers: self.save_layers(),
+            dyn_model: self.dyn_model(),
+            min_elev_deg: self.min_elev(),
+            min_cno_dbhz: self.min_cno(),
+            anti_spoofing: self.anti_spoofing(),
+            model: self.station_opt(
+                "model",
+                &self
+                    .station
+                    .receiver
+                    .as_ref()
+                    .and_then(|receiver| receiver.model.clone()),
+            ),
         }
     }
 
@@ -526,7 +1098,20 @@ Default value is GPST."
             gzip: self.matches.get_flag("gzip"),
             crinex: self.matches.get_flag("crx"),
             timescale: self.timescale(),
-            observables: self.observables(),
+            glonass_time: self.glonass_time(),
+            sampling_period: self.sampling_period(),
+            learning_epochs: self.learning_epochs(),
+            learn_observables: self.matches.get_flag("learn-observables"),
+            phase_shift: self.phase_shift(),
+            clock_offset_applied: self.matches.get_flag("rx-clock-apply"),
+            remove_clock_jumps: self.matches.get_flag("remove-clock-jumps"),
+            qc_formats: self.qc_formats(),
+            clk_pps: self.matches.get_flag("clk-pps"),
+            elev_mask_deg: self
+                .matches
+                .get_one::<String>("elev-mask")
+                .map(|mask| parse_elev_mask(mask)),
+            observables: self.observables(self.matches.get_flag("v2")),
             major: if self.matches.get_flag("v4") {
                 4
             } else if self.matches.get_flag("v2") {
@@ -534,31 +1119,22 @@ Default value is GPST."
             } else {
                 3
             },
-            country: if let Some(country) = self.matches.get_one::<String>("country") {
-                country.to_string()
-            } else {
-                "FRA".to_string()
-            },
-            agency: if let Some(agency) = self.matches.get_one::<String>("agency") {
-                Some(agency.to_string())
-            } else {
-                None
-            },
-            operator: if let Some(operator) = self.matches.get_one::<String>("operator") {
-                Some(operator.to_string())
-            } else {
-                None
-            },
+            country: self
+                .station_opt("country", &self.station.country)
+                .unwrap_or("FRA".to_string()),
+            agency: self.station_opt("agency", &self.station.agency),
+            observer: self.station_opt("observer", &self.station.observer),
+            operator: self.station_opt("operator", &self.station.operator),
+            marker: self.marker(),
+            antenna: self.antenna(),
             prefix: if let Some(prefix) = self.matches.get_one::<String>("prefix") {
                 Some(prefix.to_string())
             } else {
                 None
             },
-            name: if let Some(name) = self.matches.get_one::<String>("name") {
-                name.to_string()
-            } else {
-                "UBXR".to_string()
-            },
+            name: self
+                .station_opt("name", &self.station.name)
+                .unwrap_or("UBXR".to_string()),
             period: if let Some(period) = self.matches.get_one::<String>("period") {
                 let dt = period
                     .trim()
@@ -571,4 +1147,140 @@ Default value is GPST."
             },
         }
     }
+
+    /// Observation RINEX products. Each product customizes
+    /// the general [RinexSettings].
+    pub fn rinex_products(&self) -> Vec<RinexSettings> {
+        let settings = self.rinex_settings();
+
+        match self.matches.get_many::<String>("obs-product") {
+            Some(specs) => specs
+                .map(|spec| self.rinex_product(&settings, spec))
+                .collect(),
+            None => vec![settings],
+        }
+    }
+
+    fn rinex_product(&self, settings: &RinexSettings, spec: &str) -> RinexSettings {
+        let mut product = settings.clone();
+        let mut constellations = Option::<Vec<Constellation>>::None;
+
+        for item in spec.split(',') {
+            let item = item.trim();
+
+            let (key, value) = match item.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (item, ""),
+            };
+
+            match key {
+                "v2" => product.major = 2,
+                "v3" => product.major = 3,
+                "v4" => product.major = 4,
+                "short" => product.short_filename = true,
+                "long" => product.short_filename = false,
+                "crx" => product.crinex = true,
+                "gzip" => product.gzip = true,
+                "name" => product.name = value.to_string(),
+                "prefix" => product.prefix = Some(value.to_string()),
+                "period" => {
+                    product.period = value
+                        .parse::<Duration>()
+                        .unwrap_or_else(|e| panic!("Invalid product period: {}", e));
+                },
+                "sampling" => {
+                    let dt = value
+                        .parse::<Duration>()
+                        .unwrap_or_else(|e| panic!("Invalid product sampling: {}", e));
+
+                    if dt.total_nanoseconds() < 50_000_000 {
+                        panic!("Sampling period is limited to 50ms");
+                    }
+
+                    product.sampling_period = dt;
+                },
+                "elev-mask" => product.elev_mask_deg = Some(parse_elev_mask(value)),
+                "gnss" => {
+                    constellations = Some(
+                        value
+                            .chars()
+                            .map(|c| {
+                                Constellation::from_str(&c.to_string()).unwrap_or_else(|e| {
+                                    panic!("Invalid product constellation \"{}\": {}", c, e)
+                                })
+                            })
+                            .collect(),
+                    );
+                },
+                "" => {},
+                _ => panic!("Invalid product specification: \"{}\"", item),
+            }
+        }
+
+        product.observables = self.observables(product.major == 2);
+
+        if let Some(constellations) = constellations {
+            product
+                .observables
+                .retain(|constellation, _| constellations.contains(constellation));
+        }
+
+        product
+    }
+}
+
+/// Parses an elevation mask [deg]
+fn parse_elev_mask(mask: &str) -> f64 {
+    let mask = mask
+        .trim()
+        .parse::<f64>()
+        .unwrap_or_else(|e| panic!("Invalid elevation mask: {}", e));
+
+    if !(0.0..=90.0).contains(&mask) {
+        panic!("Elevation mask should be within 0..90 degrees");
+    }
+
+    mask
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cli;
+
+    #[test]
+    fn test_product_elev_mask() {
+        let cli = Cli::from_args([
+            "ubx2rinex",
+            "-p",
+            "/dev/ttyACM0",
+            "--gps",
+            "--elev-mask",
+            "5",
+            "--obs-product",
+            "v3,elev-mask=10",
+            "--obs-product",
+            "v2,sampling=30 s",
+        ]);
+
+        let products = cli.rinex_products();
+
+        assert_eq!(products.len(), 2);
+        assert_eq!(products[0].elev_mask_deg, Some(10.0));
+        assert_eq!(products[1].elev_mask_deg, Some(5.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Elevation mask should be within 0..90 degrees")]
+    fn test_product_invalid_elev_mask() {
+        let cli = Cli::from_args([
+            "ubx2rinex",
+            "-p",
+            "/dev/ttyACM0",
+            "--gps",
+            "--obs-product",
+            "elev-mask=95",
+        ]);
+
+        let _ = cli.rinex_products();
+    }
 }
@@ -0,0 +1,106 @@
+use std::io::{BufWriter, Write};
+
+use log::{debug, error, warn};
+
+use rinex::prelude::Epoch;
+
+use tokio::sync::mpsc::UnboundedReceiver as Rx;
+
+use crate::collecter::{fd::FileDescriptor, settings::Settings, Message};
+
+/// Maximal number of bytes we buffer, until the first timestamp
+const PENDING_MAX_BYTES: usize = 4 * 1024 * 1024;
+
+/// Raw UBX archiver: stores the stream as is,
+/// so it may be reprocessed later on.
+pub struct Collecter {
+    /// End of current file period
+    t_end: Option<Epoch>,
+    /// Bytes received before the first timestamp
+    pending: Vec<u8>,
+    rx: Rx<Message>,
+    settings: Settings,
+    fd: Option<BufWriter<FileDescriptor>>,
+}
+
+impl Collecter {
+    /// Builds new [Collecter]
+    pub fn new(settings: Settings, rx: Rx<Message>) -> Self {
+        Self {
+            rx,
+            settings,
+            fd: None,
+            t_end: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Obtain a new file descriptor
+    fn fd(&self, t: Epoch) -> FileDescriptor {
+        let filename = self.settings.ubx_filename(t);
+        debug!("{} - new UBX archive: {}", t, filename);
+        FileDescriptor::new(self.settings.gzip, &filename)
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            match self.rx.recv().await {
+                Some(msg) => match msg {
+                    Message::Raw(bytes) => match self.fd.as_mut() {
+                        Some(fd) => {
+                            if let Err(e) = fd.write_all(&bytes) {
+                                error!("UBX archiving error: {}", e);
+                            }
+                        },
+                        None => {
+                            self.pending.extend_from_slice(&bytes);
+
+                            if self.pending.len() > PENDING_MAX_BYTES {
+                                // receiver time is still not valid:
+                                // we only preserve the latest bytes.
+                                let excess = self.pending.len() - PENDING_MAX_BYTES;
+                                warn!("no valid timestamp yet: dropping {} UBX bytes", excess);
+                                self.pending.drain(..excess);
+                            }
+                        },
+                    },
+
+                    Message::Timestamp(t) => {
+                        let rotate = match self.t_end {
+                            Some(t_end) => t >= t_end,
+                            None => true,
+                        };
+
+                        if rotate {
+                            self.release_file();
+
+                            let mut fd = BufWriter::new(self.fd(t));
+
+                            if let Err(e) = fd.write_all(&self.pending) {
+                                error!("UBX archiving error: {}", e);
+                            }
+
+                            self.pending.clear();
+
+                            self.fd = Some(fd);
+                            self.t_end = Some(self.settings.period_start(t) + self.settings.period);
+                        }
+                    },
+
+                    Message::Shutdown => {
+                        self.release_file();
+                        return;
+                    },
+                    _ => {},
+                },
+                None => {},
+            }
+        }
+    }
+
+    fn release_file(&mut self) {
+        if let Some(mut fd) = self.fd.take() {
+            let _ = fd.flush();
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::io::{BufWriter, Write};
+
+use log::{debug, error};
+
+use rinex::prelude::{Constellation, Epoch, TimeScale};
+
+use tokio::sync::mpsc::Receiver as Rx;
+
+use crate::{
+    collecter::{
+        decimation::Decimator, fd::FileDescriptor, header::header_record, settings::Settings,
+        Message,
+    },
+    UbloxSettings,
+};
+
+/// Receiver clock state, as reported by NAV-CLOCK
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ClockState {
+    /// [Epoch] of this state
+    pub epoch: Epoch,
+    /// Clock bias [s]
+    pub bias: f64,
+    /// Clock bias uncertainty [s]
+    pub bias_sigma: f64,
+    /// Clock drift [s/s]
+    pub drift: f64,
+    /// Clock drift uncertainty [s/s]
+    pub drift_sigma: f64,
+}
+
+/// Formats a value like Fortran E19.12 does (2 digit exponent)
+fn fortran_e19(value: f64) -> String {
+    let formatted = format!("{:.12E}", value);
+
+    let formatted = match formatted.split_once('E') {
+        Some((mantissa, exponent)) => {
+            let exponent = exponent.parse::<i32>().unwrap_or_default();
+            let sign = if exponent < 0 { '-' } else { '+' };
+            format!("{}E{}{:02}", mantissa, sign, exponent.abs())
+        },
+        None => formatted,
+    };
+
+    format!("{:>19}", formatted)
+}
+
+impl ClockState {
+    /// Formats this [ClockState] as RINEX AR record
+    fn format<W: Write>(&self, name: &str, w: &mut W) -> std::io::Result<()> {
+        let (y, m, d, hh, mm, ss, nanos) = self.epoch.to_gregorian(self.epoch.time_scale);
+        let seconds = ss as f64 + nanos as f64 * 1.0E-9;
+
+        writeln!(
+            w,
+            "AR {:<4} {:04} {:02} {:02} {:02} {:02} {:9.6} {:2}   {} {}",
+            name,
+            y,
+            m,
+            d,
+            hh,
+            mm,
+            seconds,
+            4,
+            fortran_e19(self.bias),
+            fortran_e19(self.bias_sigma),
+        )?;
+
+        writeln!(
+            w,
+            " {} {}",
+            fortran_e19(self.drift),
+            fortran_e19(self.drift_sigma)
+        )
+    }
+}
+
+/// RINEX Clock [Collecter], that produces receiver clock (AR) records
+pub struct Collecter {
+    /// End of current file period
+    t_end: Option<Epoch>,
+    /// Epoch [Decimator]
+    decimator: Decimator,
+    /// Latest time pulse quantization error [s]
+    qerr: Option<f64>,
+    rx: Rx<Message>,
+    settings: Settings,
+    ubx_settings: UbloxSettings,
+    /// Receiver position (ECEF [m]), when known
+    rx_position: Option<(f64, f64, f64)>,
+    fd: Option<BufWriter<FileDescriptor>>,
+}
+
+impl Collecter {
+    /// Builds new [Collecter]
+    pub fn new(settings: Settings, ublox: UbloxSettings, rx: Rx<Message>) -> Self {
+        Self {
+            rx,
+            decimator: Decimator::new(settings.sampling_period, ublox.measurement_period),
+            settings,
+            ubx_settings: ublox,
+            fd: None,
+            t_end: None,
+            qerr: None,
+            rx_position: None,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            match self.rx.recv().await {
+                Some(msg) => match msg {
+                    Message::QuantizationError(qerr) => {
+                        self.qerr = Some(qerr);
+                    },
+                    Message::Position(ecef) | Message::FixedPosition(ecef) => {
+                        self.rx_position = Some(ecef);
+                    },
+                    Message::ClockState(mut state) => {
+                        if !self.decimator.keep(state.epoch) {
+                            continue;
+                        }
+
+                        // time pulse quantization (sawtooth) correction
+                        if let Some(qerr) = self.qerr.take() {
+                            if self.settings.clk_pps {
+                                state.bias += qerr;
+                            }
+                        }
+
+                        let rotate = match self.t_end {
+                            Some(t_end) => state.epoch >= t_end,
+                            None => true,
+                        };
+
+                        if rotate {
+                            self.release_file();
+                            self.release_header(state.epoch);
+                        }
+
+                        if let Some(fd) = self.fd.as_mut() {
+                            if let Err(e) = state.format(&self.station_name(), fd) {
+                                error!("{} - clock formatting issue: {}", state.epoch, e);
+                            }
+                            let _ = fd.flush();
+                        }
+                    },
+                    Message::Shutdown => {
+                        self.release_file();
+                        return;
+                    },
+                    _ => {},
+                },
+                None => {},
+            }
+        }
+    }
+
+    fn release_header(&mut self, t: Epoch) {
+        let filename = self.settings.clk_filename(t);
+        debug!("{} - new RINEX Clock: {}", t, filename);
+
+        let mut fd = BufWriter::new(FileDescriptor::new(self.settings.gzip, &filename));
+
+        if let Err(e) = fd.write_all(self.header().as_bytes()) {
+            error!("RINEX Clock header: {}", e);
+        }
+
+        self.fd = Some(fd);
+        self.t_end = Some(self.settings.period_start(t) + self.settings.period);
+    }
+
+    fn release_file(&mut self) {
+        if let Some(mut fd) = self.fd.take() {
+            let _ = fd.flush();
+        }
+    }
+
+    /// Station name, as described by RINEX Clock (4 characters)
+    fn station_name(&self) -> String {
+        self.settings.name.chars().take(4).collect()
+    }
+
+    fn header(&self) -> String {
+        let timescale = self.settings.timescale;
+
+        let system = match timescale {
+            _ if self.settings.glonass_time => Constellation::Glonass,
+            TimeScale::GPST => Constellation::GPS,
+            TimeScale::GST => Constellation::Galileo,
+            TimeScale::BDT => Constellation::BeiDou,
+            _ => Constellation::Mixed,
+        };
+
+        let now = Epoch::now()
+            .unwrap_or_else(|e| panic!("Failed to determine system time: {}", e))
+            .to_time_scale(TimeScale::UTC);
+
+        let (y, m, d, hh, mm, ss, _) = now.to_gregorian_utc();
+
+        let mut header = header_record(
+            &format!("{:9.2}           C                   {:x}", 3.0, system),
+            "RINEX VERSION / TYPE",
+        );
+
+        header.push_str(&header_record(
+            &format!(
+                "{:<20}{:<20}{:04}{:02}{:02} {:02}{:02}{:02} UTC",
+                "ubx2rinex",
+                self.settings.operator.as_deref().unwrap_or(""),
+                y,
+                m,
+                d,
+                hh,
+                mm,
+                ss
+            ),
+            "PGM / RUN BY / DATE",
+        ));
+
+        if let Some(model) = &self.ubx_settings.model {
+            header.push_str(&header_record(&format!("RECEIVER: {}", model), "COMMENT"));
+        }
+
+        if self.settings.clk_pps {
+            header.push_str(&header_record(
+                "CLOCK BIAS REFERS TO THE PPS OUTPUT (TIM-TP QERR APPLIED)",
+                "COMMENT",
+            ));
+        }
+
+        let time_system = match timescale {
+            _ if self.settings.glonass_time => "GLO".to_string(),
+            TimeScale::GPST => "GPS".to_string(),
+            TimeScale::GST => "GAL".to_string(),
+            ts => ts.to_string(),
+        };
+
+        header.push_str(&header_record(
+            &format!("   {:<3}", time_system),
+            "TIME SYSTEM ID",
+        ));
+
+        header.push_str(&header_record("     1    AR", "# / TYPES OF DATA"));
+
+        if let Some(agency) = &self.settings.agency {
+            header.push_str(&header_record(
+                &format!("{:<3}  {}", "UBX", agency),
+                "ANALYSIS CENTER",
+            ));
+        }
+
+        // station coordinates are only declared once known
+        if let Some((x, y, z)) = self.rx_position {
+            header.push_str(&header_record("     1", "# OF SOLN STA / TRF"));
+
+            let number = self
+                .settings
+                .marker
+                .as_ref()
+                .and_then(|marker| marker.number())
+                .unwrap_or_default();
+
+            header.push_str(&header_record(
+                &format!(
+                    "{:<4} {:<20}{:11} {:11} {:11}",
+                    self.station_name(),
+                    number,
+                    (x * 1.0E3).round() as i64,
+                    (y * 1.0E3).round() as i64,
+                    (z * 1.0E3).round() as i64,
+                ),
+                "SOLN STA NAME / NUM",
+            ));
+        }
+
+        header.push_str(&header_record("", "END OF HEADER"));
+        header
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fortran_e19, ClockState};
+    use hifitime::prelude::Epoch;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_ar_record() {
+        assert_eq!(fortran_e19(-1.25E-4), "-1.250000000000E-04");
+        assert_eq!(fortran_e19(0.0), " 0.000000000000E+00");
+
+        let state = ClockState {
+            epoch: Epoch::from_str("2025-02-25T20:30:30 GPST").unwrap(),
+            bias: -1.25E-4,
+            bias_sigma: 1.0E-9,
+            drift: 2.5E-10,
+            drift_sigma: 1.0E-12,
+        };
+
+        let mut content = Vec::new();
+        state.format("UBXR", &mut content).unwrap();
+
+        let content = String::from_utf8(content).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[0],
+            "AR UBXR 2025 02 25 20 30 30.000000  4   -1.250000000000E-04  1.000000000000E-09"
+        );
+
+        // first value starts at column 41
+        assert_eq!(lines[0].find("-1.25"), Some(40));
+
+        assert_eq!(lines[1], "  2.500000000000E-10  1.000000000000E-12");
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    io::{BufWriter, Write},
+    marker::PhantomData,
+};
+
+use log::{debug, error};
+
+use rinex::prelude::Epoch;
+
+use tokio::sync::mpsc::Receiver as Rx;
+
+use crate::collecter::{fd::FileDescriptor, settings::Settings, Message};
+
+/// Format of an auxiliary dataset
+pub trait DatasetFormat: Copy {
+    /// File extension
+    fn extension(&self) -> &'static str;
+
+    /// Content written at the beginning of each file
+    fn header(&self) -> &'static str;
+
+    /// Content written at the end of each file
+    fn footer(&self) -> &'static str {
+        ""
+    }
+}
+
+/// Record of an auxiliary dataset
+pub trait DatasetRecord: Sized {
+    type Format: DatasetFormat;
+
+    /// Dataset name, for logging purposes
+    const NAME: &'static str;
+
+    /// Picks the record this [Message] may carry
+    fn from_message(msg: Message) -> Option<Self>;
+
+    /// [Epoch] of this record
+    fn epoch(&self) -> Epoch;
+
+    /// Formats this record
+    fn format<W: Write>(&self, format: Self::Format, w: &mut W) -> std::io::Result<()>;
+}
+
+/// Auxiliary dataset [Collecter]: one file per format,
+/// rotated on the file period.
+pub struct Collecter<R: DatasetRecord> {
+    /// End of current file period
+    t_end: Option<Epoch>,
+    rx: Rx<Message>,
+    settings: Settings,
+    formats: Vec<R::Format>,
+    fds: Vec<(R::Format, BufWriter<FileDescriptor>)>,
+    record: PhantomData<R>,
+}
+
+impl<R: DatasetRecord> Collecter<R> {
+    /// Builds new [Collecter]
+    pub fn new(settings: Settings, formats: Vec<R::Format>, rx: Rx<Message>) -> Self {
+        Self {
+            rx,
+            settings,
+            formats,
+            t_end: None,
+            fds: Vec::new(),
+            record: PhantomData,
+        }
+    }
+
+    pub async fn run(&mut self) {
+        loop {
+            match self.rx.recv().await {
+                Some(Message::Shutdown) | None => {
+                    self.release_files();
+                    return;
+                },
+                Some(msg) => {
+                    if let Some(record) = R::from_message(msg) {
+                        self.write(&record);
+                    }
+                },
+            }
+        }
+    }
+
+    fn write(&mut self, record: &R) {
+        let t = record.epoch().to_time_scale(self.settings.timescale);
+
+        let rotate = match self.t_end {
+            Some(t_end) => t >= t_end,
+            None => true,
+        };
+
+        if rotate {
+            self.release_files();
+            self.open_files(t);
+        }
+
+        for (format, fd) in self.fds.iter_mut() {
+            if let Err(e) = record.format(*format, fd) {
+                error!("{} - {} formatting issue: {}", t, R::NAME, e);
+            }
+            let _ = fd.flush();
+        }
+    }
+
+    fn open_files(&mut self, t: Epoch) {
+        for format in self.formats.iter() {
+            let filename = self.settings.aux_filename(t, format.extension());
+            debug!("{} - new {}: {}", t, R::NAME, filename);
+
+            let mut fd = BufWriter::new(FileDescriptor::new(false, &filename));
+
+            if let Err(e) = fd.write_all(format.header().as_bytes()) {
+                error!("{} header: {}", R::NAME, e);
+            }
+
+            self.fds.push((*format, fd));
+        }
+
+        self.t_end = Some(self.settings.period_start(t) + self.settings.period);
+    }
+
+    fn release_files(&mut self) {
+        for (format, mut fd) in self.fds.drain(..) {
+            let _ = fd.write_all(format.footer().as_bytes());
+            let _ = fd.flush();
+        }
+    }
+}
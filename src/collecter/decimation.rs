@@ -0,0 +1,65 @@
+use rinex::prelude::{Duration, Epoch};
+
+/// [Decimator] only keeps the epochs aligned to its interval,
+/// whatever the receiver measurement rate.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimator {
+    /// Decimation interval (in nanoseconds)
+    interval: i128,
+    /// Alignment tolerance (in nanoseconds)
+    tolerance: i128,
+}
+
+impl Decimator {
+    /// Builds a new [Decimator] for this interval, and this receiver measurement period.
+    pub fn new(interval: Duration, measurement_period: Duration) -> Self {
+        Self {
+            interval: interval.total_nanoseconds(),
+            tolerance: measurement_period.total_nanoseconds() / 2,
+        }
+    }
+
+    /// Returns true if this [Epoch] should be kept
+    pub fn keep(&self, t: Epoch) -> bool {
+        if self.interval <= 2 * self.tolerance {
+            return true;
+        }
+
+        let (_, tow_nanos) = t.to_time_of_week();
+
+        let residual = tow_nanos as i128 % self.interval;
+        let distance = residual.min(self.interval - residual);
+
+        distance < self.tolerance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Decimator;
+    use hifitime::prelude::{Duration, Epoch};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_decimation() {
+        let decim = Decimator::new(Duration::from_seconds(30.0), Duration::from_seconds(1.0));
+
+        for (t, kept) in [
+            ("2025-02-25T20:30:00 GPST", true),
+            ("2025-02-25T20:30:00.006 GPST", true),
+            ("2025-02-25T20:30:01.006 GPST", false),
+            ("2025-02-25T20:30:29.006 GPST", false),
+            ("2025-02-25T20:30:29.994 GPST", true),
+            ("2025-02-25T20:30:30.006 GPST", true),
+            ("2025-02-25T20:31:15.006 GPST", false),
+        ] {
+            let t = Epoch::from_str(t).unwrap();
+            assert_eq!(decim.keep(t), kept, "failed for {}", t);
+        }
+
+        // no decimation needed
+        let decim = Decimator::new(Duration::from_seconds(1.0), Duration::from_seconds(1.0));
+        let t = Epoch::from_str("2025-02-25T20:30:01.006 GPST").unwrap();
+        assert!(decim.keep(t));
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Result, Write},
+};
+
+use rinex::prelude::{obs::EpochFlag, Epoch};
+
+use crate::collecter::header::header_record;
+
+/// [Event] record, which RINEX describes with special epoch flags,
+/// possibly followed by comments.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// [Epoch] of the event
+    pub epoch: Epoch,
+    /// [EpochFlag] describing this event
+    pub flag: EpochFlag,
+    /// Comments following the event
+    pub comments: Vec<String>,
+}
+
+impl Event {
+    /// Builds a new [Event] with comments, declared as header information.
+    pub fn comments(epoch: Epoch, comments: &[&str]) -> Self {
+        Self {
+            epoch,
+            flag: EpochFlag::HeaderInformationFollows,
+            comments: comments.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    /// Builds a new external [Event] (epoch flag 5)
+    pub fn external(epoch: Epoch) -> Self {
+        Self {
+            epoch,
+            flag: EpochFlag::ExternalEvent,
+            comments: Vec::new(),
+        }
+    }
+
+    fn flag_value(&self) -> u8 {
+        match self.flag {
+            EpochFlag::Ok => 0,
+            EpochFlag::PowerFailure => 1,
+            EpochFlag::AntennaBeingMoved => 2,
+            EpochFlag::NewSiteOccupation => 3,
+            EpochFlag::HeaderInformationFollows => 4,
+            EpochFlag::ExternalEvent => 5,
+            EpochFlag::CycleSlip => 6,
+        }
+    }
+
+    /// Formats this [Event] record
+    pub fn format<W: Write>(&self, v2: bool, w: &mut W) -> Result<()> {
+        let (y, m, d, hh, mm, ss, nanos) = self.epoch.to_gregorian(self.epoch.time_scale);
+        let seconds = ss as f64 + nanos as f64 * 1.0E-9;
+
+        if v2 {
+            writeln!(
+                w,
+                " {:02} {:2} {:2} {:2} {:2}{:11.7}  {}{:3}",
+                y % 100,
+                m,
+                d,
+                hh,
+                mm,
+                seconds,
+                self.flag_value(),
+                self.comments.len(),
+            )?;
+        } else {
+            writeln!(
+                w,
+                "> {:04} {:02} {:02} {:02} {:02}{:11.7}  {}{:3}",
+                y,
+                m,
+                d,
+                hh,
+                mm,
+                seconds,
+                self.flag_value(),
+                self.comments.len(),
+            )?;
+        }
+
+        for comment in self.comments.iter() {
+            write!(w, "{}", header_record(comment, "COMMENT"))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// CSV log of the external events (time marks)
+pub struct EventLog {
+    w: BufWriter<File>,
+}
+
+impl EventLog {
+    /// Creates a new [EventLog]
+    pub fn new(path: &str) -> Self {
+        let fd =
+            File::create(path).unwrap_or_else(|e| panic!("Failed to open \"{}\": {}", path, e));
+
+        let mut w = BufWriter::new(fd);
+
+        writeln!(w, "epoch,channel,edge,count,accuracy_ns")
+            .unwrap_or_else(|e| panic!("Failed to write \"{}\": {}", path, e));
+
+        Self { w }
+    }
+
+    /// Logs a new event
+    pub fn log(&mut self, t: Epoch, channel: u8, edge: &str, count: u16, accuracy_ns: u32) {
+        let _ = writeln!(
+            self.w,
+            "{},{},{},{},{}",
+            t, channel, edge, count, accuracy_ns
+        );
+        let _ = self.w.flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Event;
+    use hifitime::prelude::Epoch;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_event_format() {
+        let t = Epoch::from_str("2025-02-25T20:30:30 GPST").unwrap();
+
+        let mut content = Vec::new();
+        Event::external(t).format(false, &mut content).unwrap();
+
+        assert_eq!(
+            String::from_utf8(content).unwrap(),
+            "> 2025 02 25 20 30 30.0000000  5  0\n"
+        );
+
+        let mut content = Vec::new();
+
+        Event::comments(t, &["RECEIVER CLOCK RESET"])
+            .format(true, &mut content)
+            .unwrap();
+
+        let content = String::from_utf8(content).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], " 25  2 25 20 30 30.0000000  4  1");
+        assert!(lines[1].starts_with("RECEIVER CLOCK RESET"));
+        assert!(lines[1].ends_with("COMMENT"));
+    }
+}
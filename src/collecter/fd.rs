@@ -1,5 +1,8 @@
 use flate2::{write::GzEncoder, Compression};
-use std::fs::File;
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom, Write},
+};
 
 pub enum FileDescriptor {
     Plain(File),
@@ -34,4 +37,22 @@ impl FileDescriptor {
             Self::Plain(fd)
         }
     }
+
+    /// Overwrites previously written content, at given offset (in bytes).
+    /// This is only feasible on [FileDescriptor::Plain] files.
+    pub fn patch(&mut self, offset: u64, data: &[u8]) -> std::io::Result<()> {
+        match self {
+            Self::Plain(fd) => {
+                fd.flush()?;
+                fd.seek(SeekFrom::Start(offset))?;
+                fd.write_all(data)?;
+                fd.seek(SeekFrom::End(0))?;
+                Ok(())
+            },
+            Self::Gzip(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "can't patch compressed content",
+            )),
+        }
+    }
 }
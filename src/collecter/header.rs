@@ -0,0 +1,147 @@
+use std::io::BufWriter;
+
+use rinex::prelude::{Header, Observable};
+
+/// Formats [Header] into a byte buffer
+pub fn format_header(header: &Header) -> Vec<u8> {
+    let mut buf = BufWriter::new(Vec::<u8>::new());
+
+    header.format(&mut buf).unwrap_or_else(|e| {
+        panic!(
+            "RINEX header formatting: {}. Aborting (avoiding corrupt file)",
+            e
+        )
+    });
+
+    buf.into_inner()
+        .unwrap_or_else(|e| panic!("RINEX header formatting: {}", e))
+}
+
+/// Locates the header line that contains said label.
+/// Returns (offset, length) in bytes.
+pub fn find_line(content: &[u8], label: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    let content = String::from_utf8_lossy(content);
+
+    for line in content.split_inclusive('\n') {
+        if line.contains(label) {
+            return Some((offset, line.len()));
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Formats a header record, that the RINEX library does not support:
+/// content is padded to 60 columns, followed by the label.
+pub fn header_record(content: &str, label: &str) -> String {
+    format!("{:<60}{}\n", content, label)
+}
+
+/// Formats the GLONASS COD/PHS/BIS record, that declares the GLONASS codes
+/// we observe (among C1C, C1P, C2C and C2P). u-Blox does not specify
+/// the code-phase biases: values are left blank (unknown).
+pub fn glonass_cod_phs_bis_record(observables: &[Observable]) -> String {
+    let content = ["C1C", "C1P", "C2C", "C2P"]
+        .iter()
+        .filter(|code| {
+            observables
+                .iter()
+                .any(|observable| observable.to_string() == **code)
+        })
+        .map(|code| format!(" {}         ", code))
+        .collect::<String>();
+
+    header_record(content.trim_end(), "GLONASS COD/PHS/BIS")
+}
+
+/// Replaces the time system of the record that contains said label.
+/// This is used for the time systems that [Epoch] can't describe, like GLONASST.
+pub fn replace_time_system(content: &mut [u8], label: &str, from: &str, to: &str) {
+    if from.len() != to.len() {
+        return;
+    }
+
+    if let Some((offset, len)) = find_line(content, label) {
+        let line = String::from_utf8_lossy(&content[offset..offset + len]).to_string();
+
+        if let Some(pos) = line.find(from) {
+            content[offset + pos..offset + pos + from.len()].copy_from_slice(to.as_bytes());
+        }
+    }
+}
+
+/// Inserts custom records right before END OF HEADER
+pub fn insert_records(content: &mut Vec<u8>, records: &[String]) {
+    if records.is_empty() {
+        return;
+    }
+
+    let offset = find_line(content, "END OF HEADER")
+        .map(|(offset, _)| offset)
+        .unwrap_or(content.len());
+
+    let records = records.concat();
+    content.splice(offset..offset, records.bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        find_line, glonass_cod_phs_bis_record, header_record, insert_records, replace_time_system,
+    };
+    use rinex::prelude::Observable;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_custom_records() {
+        let record = header_record(" C1C         C1P", "GLONASS COD/PHS/BIS");
+        assert_eq!(record.find("GLONASS COD/PHS/BIS"), Some(60));
+
+        let mut content = format!(
+            "{}{}",
+            header_record("", "COMMENT"),
+            header_record("", "END OF HEADER")
+        )
+        .into_bytes();
+
+        insert_records(&mut content, &[record]);
+
+        assert_eq!(find_line(&content, "COMMENT"), Some((0, 68)));
+        assert_eq!(find_line(&content, "GLONASS COD/PHS/BIS"), Some((68, 80)));
+        assert_eq!(find_line(&content, "END OF HEADER"), Some((148, 74)));
+    }
+
+    #[test]
+    fn test_glonass_cod_phs_bis() {
+        let observables = ["C1C", "L1C", "D1C", "C2C", "L2C"]
+            .iter()
+            .map(|observable| Observable::from_str(observable).unwrap())
+            .collect::<Vec<_>>();
+
+        let record = glonass_cod_phs_bis_record(&observables);
+
+        assert!(record.starts_with(" C1C          C2C "));
+        assert!(!record.contains("C1P"));
+        assert_eq!(record.find("GLONASS COD/PHS/BIS"), Some(60));
+
+        let record = glonass_cod_phs_bis_record(&[]);
+        assert_eq!(record.find("GLONASS COD/PHS/BIS"), Some(60));
+    }
+
+    #[test]
+    fn test_glonass_time_system() {
+        let mut content = header_record(
+            "  2025     2    25    20    30   30.0000000     UTC",
+            "TIME OF FIRST OBS",
+        )
+        .into_bytes();
+
+        replace_time_system(&mut content, "TIME OF FIRST OBS", "UTC", "GLO");
+
+        let content = String::from_utf8(content).unwrap();
+        assert!(content.starts_with("  2025     2    25    20    30   30.0000000     GLO"));
+        assert!(content.ends_with("TIME OF FIRST OBS\n"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use rinex::prelude::{Epoch, SV};
+
+use crate::utils::SPEED_OF_LIGHT_M_S;
+
+/// One millisecond, expressed in meters
+const MS_M: f64 = SPEED_OF_LIGHT_M_S * 1.0E-3;
+
+/// One signal [Sample], expressed in meters
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// Pseudo range [m]
+    pub pr: f64,
+    /// Phase range [m], when tracked
+    pub phase: Option<f64>,
+    /// Range rate [m/s], deduced from doppler
+    pub range_rate: f64,
+}
+
+/// [JumpDetector] detects the millisecond jumps of the receiver clock,
+/// by comparing the evolution of the pseudo range and phase observations
+/// to the one predicted by the doppler observations, on all signals.
+#[derive(Debug, Default)]
+pub struct JumpDetector {
+    /// Previous epoch and its samples, per signal
+    prev: Option<(Epoch, HashMap<(SV, String), Sample>)>,
+    /// Accumulated pseudo range jumps [ms]
+    pub pr_jumps_ms: i64,
+    /// Accumulated phase jumps [ms]
+    pub phase_jumps_ms: i64,
+}
+
+impl JumpDetector {
+    /// Resets the detector, for example on clock reset
+    pub fn reset(&mut self) {
+        self.prev = None;
+    }
+
+    /// Detects jumps at this [Epoch], returns jumps on (pseudo range, phase) in ms.
+    pub fn detect(&mut self, t: Epoch, samples: HashMap<(SV, String), Sample>) -> (i64, i64) {
+        let (t_prev, prev) = match self.prev.replace((t, samples)) {
+            Some(prev) => prev,
+            None => return (0, 0),
+        };
+
+        let (_, current) = self.prev.as_ref().unwrap();
+
+        let dt = (t - t_prev).to_seconds();
+
+        let mut pr_residuals = Vec::new();
+        let mut phase_residuals = Vec::new();
+
+        for (signal, sample) in current.iter() {
+            if let Some(prev) = prev.get(signal) {
+                let expected = (sample.range_rate + prev.range_rate) / 2.0 * dt;
+
+                pr_residuals.push(sample.pr - prev.pr - expected);
+
+                if let (Some(phase), Some(prev_phase)) = (sample.phase, prev.phase) {
+                    phase_residuals.push(phase - prev_phase - expected);
+                }
+            }
+        }
+
+        let pr_jump = Self::jump_ms(&mut pr_residuals);
+        let phase_jump = Self::jump_ms(&mut phase_residuals);
+
+        self.pr_jumps_ms += pr_jump;
+        self.phase_jumps_ms += phase_jump;
+
+        (pr_jump, phase_jump)
+    }
+
+    /// Determines the common jump (in ms) of these residuals [m],
+    /// from their median value.
+    fn jump_ms(residuals: &mut [f64]) -> i64 {
+        if residuals.is_empty() {
+            return 0;
+        }
+
+        residuals.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let median = residuals[residuals.len() / 2];
+        let jump = (median / MS_M).round();
+
+        if (median - jump * MS_M).abs() < 0.1 * MS_M {
+            jump as i64
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JumpDetector, Sample, MS_M};
+    use hifitime::prelude::{Duration, Epoch};
+    use rinex::prelude::SV;
+    use std::{collections::HashMap, str::FromStr};
+
+    fn samples(ranges: &[(SV, f64, f64)], pr_offset: f64) -> HashMap<(SV, String), Sample> {
+        ranges
+            .iter()
+            .map(|(sv, range, range_rate)| {
+                (
+                    (*sv, "1C".to_string()),
+                    Sample {
+                        pr: range + pr_offset,
+                        phase: Some(*range),
+                        range_rate: *range_rate,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_ms_jump_detection() {
+        let g01 = SV::from_str("G01").unwrap();
+        let g08 = SV::from_str("G08").unwrap();
+        let g15 = SV::from_str("G15").unwrap();
+
+        let t0 = Epoch::from_str("2025-02-25T20:30:00 GPST").unwrap();
+        let t = |k: f64| t0 + Duration::from_seconds(30.0 * k);
+
+        let mut detector = JumpDetector::default();
+
+        let ranges = [
+            (g01, 20_000_000.0, 100.0),
+            (g08, 22_000_000.0, -250.0),
+            (g15, 24_000_000.0, 500.0),
+        ];
+
+        assert_eq!(detector.detect(t(0.0), samples(&ranges, 0.0)), (0, 0));
+
+        let propagate = |ranges: &[(SV, f64, f64)]| {
+            ranges
+                .iter()
+                .map(|(sv, range, rate)| (*sv, range + rate * 30.0, *rate))
+                .collect::<Vec<_>>()
+        };
+
+        let ranges = propagate(&ranges);
+        assert_eq!(detector.detect(t(1.0), samples(&ranges, 0.0)), (0, 0));
+
+        // receiver clock jumps by +1 ms, on pseudo range only
+        let ranges = propagate(&ranges);
+        assert_eq!(detector.detect(t(2.0), samples(&ranges, MS_M)), (1, 0));
+
+        let ranges = propagate(&ranges);
+        assert_eq!(detector.detect(t(3.0), samples(&ranges, MS_M)), (0, 0));
+
+        assert_eq!(detector.pr_jumps_ms, 1);
+        assert_eq!(detector.phase_jumps_ms, 0);
+
+        detector.reset();
+
+        let ranges = propagate(&ranges);
+        assert_eq!(detector.detect(t(4.0), samples(&ranges, 0.0)), (0, 0));
+    }
+}
@@ -5,14 +5,32 @@ use rinex::{
 
 mod fd;
 
+pub mod archive;
+pub mod clock;
+pub mod dataset;
+pub mod decimation;
 pub mod ephemeris;
+pub mod event;
+pub mod header;
+pub mod jumps;
 pub mod navigation;
 pub mod observation;
+pub mod phase_shift;
+pub mod pvt;
+pub mod qc;
 pub mod rawxm;
 pub mod settings;
+pub mod skyplot;
 
+use clock::ClockState;
+use event::Event;
+use pvt::Pvt;
 use rawxm::Rawxm;
+use skyplot::SkyEpoch;
 
+use tokio::sync::mpsc::{error::TrySendError, Sender};
+
+#[derive(Clone)]
 pub enum Message {
     /// [Message::Shutdown] catches Ctrl+C interruptions
     Shutdown,
@@ -20,12 +38,45 @@ pub enum Message {
     EndofEpoch(Epoch),
     /// [Epoch] update
     Timestamp(Epoch),
-    /// New clock state [s]
-    Clock(f64),
+    /// Receiver clock reset, at this [Epoch]
+    ClockReset(Epoch),
+    /// New clock state: bias [s] and drift [s/s]
+    Clock((f64, f64)),
+    /// Complete [ClockState], for the RINEX Clock product
+    ClockState(ClockState),
+    /// Time pulse quantization error [s]
+    QuantizationError(f64),
     /// New [Rawxm] measurements
     Measurement(Rawxm),
-    /// Firmware version notification
-    FirmwareVersion(String),
+    /// New receiver position (ECEF [m])
+    Position((f64, f64, f64)),
+    /// Surveyed (or known) receiver position (ECEF [m]),
+    /// that prevails over the averaged position.
+    FixedPosition((f64, f64, f64)),
+    /// New [Event] to be declared
+    Event(Event),
+    /// Raw bytes, as read from the device
+    Raw(Vec<u8>),
+    /// New [Pvt] solution
+    Pvt(Pvt),
+    /// Satellite states ([SkyEpoch]), as reported by NAV-SAT
+    Satellites(SkyEpoch),
     /// Ephemeris publication
     Ephemeris((Epoch, SV, Ephemeris)),
 }
+
+/// Publishes this [Message] to all collecters sharing the stream.
+/// Returns the first error that was encountered.
+pub fn broadcast(txs: &[Sender<Message>], msg: Message) -> Result<(), TrySendError<Message>> {
+    let mut ret = Ok(());
+
+    for tx in txs.iter() {
+        if let Err(e) = tx.try_send(msg.clone()) {
+            if ret.is_ok() {
+                ret = Err(e);
+            }
+        }
+    }
+
+    ret
+}
@@ -19,8 +19,6 @@ use crate::{
 };
 
 pub struct Collecter {
-    /// T0: initial deploy time, updated on each file release.
-    t0: Epoch,
     rx: Rx<Message>,
     shutdown: WatchRx<bool>,
     settings: Settings,
@@ -33,7 +31,6 @@ pub struct Collecter {
 impl Collecter {
     /// Builds new [Collecter]
     pub fn new(
-        t0: Epoch,
         settings: Settings,
         ublox: UbloxSettings,
         shutdown: WatchRx<bool>,
@@ -43,16 +40,9 @@ impl Collecter {
 
         let mut header = Header::basic_nav().with_version(version);
 
-        if let Some(operator) = &settings.operator {
-            header.observer = Some(operator.clone());
-        }
-
-        if let Some(agency) = &settings.agency {
-            header.agency = Some(agency.to_string());
-        }
+        settings.customize_header(&mut header);
 
         Self {
-            t0,
             rx,
             settings,
             header,
@@ -63,9 +53,8 @@ impl Collecter {
         }
     }
 
-    /// Obtain a new file descriptor
-    fn fd(&self) -> FileDescriptor {
-        let t = self.t0;
+    /// Obtain a new file descriptor, named after this receiver [Epoch]
+    fn fd(&self, t: Epoch) -> FileDescriptor {
         let filename = self.settings.filename(true, t);
         FileDescriptor::new(self.settings.gzip, &filename)
     }
@@ -76,7 +65,7 @@ impl Collecter {
                 Some(msg) => match msg {
                     Message::EndofEpoch(t) => {
                         if self.fd.is_none() {
-                            self.release_header();
+                            self.release_header(t);
                         }
 
                         let fd = self.fd.as_mut().unwrap();
@@ -91,10 +80,6 @@ impl Collecter {
                         }
                     },
 
-                    Message::FirmwareVersion(version) => {
-                        self.ubx_settings.firmware = Some(version.to_string());
-                    },
-
                     Message::Ephemeris((t, sv, eph)) => {
                         let key = NavKey {
                             epoch: t,
@@ -124,9 +109,9 @@ impl Collecter {
         }
     }
 
-    fn release_header(&mut self) {
+    fn release_header(&mut self, t: Epoch) {
         // obtain a file descriptor
-        let mut fd = BufWriter::new(self.fd());
+        let mut fd = BufWriter::new(self.fd(t));
 
         self.header.format(&mut fd).unwrap_or_else(|e| {
             panic!(
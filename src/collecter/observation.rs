@@ -1,30 +1,96 @@
 use std::{
+    collections::HashMap,
     io::{BufWriter, Write},
     str::FromStr,
 };
 
 use rinex::{
-    observation::{ClockObservation, HeaderFields as ObsHeader},
+    hardware::Receiver,
+    observation::{ClockObservation, HeaderFields as ObsHeader, LliFlags},
     prelude::{
         obs::{EpochFlag, ObsKey, Observations, SignalObservation},
-        Epoch, Header, Observable, RinexType, CRINEX,
+        Constellation, Duration, Epoch, Header, Observable, RinexType, CRINEX, SV,
     },
 };
 
 use tokio::{sync::mpsc::Receiver as Rx, sync::watch::Receiver as WatchRx};
 
-use log::error;
+use log::{debug, error, warn};
 
 use crate::{
-    collecter::{fd::FileDescriptor, settings::Settings, Message},
+    collecter::{
+        decimation::Decimator,
+        event::Event,
+        fd::FileDescriptor,
+        header::{
+            find_line, format_header, glonass_cod_phs_bis_record, header_record, insert_records,
+            replace_time_system,
+        },
+        jumps::{JumpDetector, Sample},
+        phase_shift::{phase_shift, phase_shift_record, PhaseShiftMode},
+        qc::{CnoSamples, QcFormat, QcReport},
+        rawxm::Rawxm,
+        settings::Settings,
+        skyplot::{SatelliteState, SvHealth},
+        Message,
+    },
+    utils::{carrier_frequency, to_signal_code, SPEED_OF_LIGHT_M_S},
     UbloxSettings,
 };
 
+/// Minimal validity of the reported elevations [s]: older
+/// [SatelliteState]s are considered unknown by the elevation mask.
+const ELEVATION_VALIDITY_S: f64 = 30.0;
+
 pub struct Collecter {
     t: Option<Epoch>,
     t0: Option<Epoch>,
+    /// Last released [Epoch]
+    t_last: Option<Epoch>,
+    /// End of current file period
+    t_end: Option<Epoch>,
+    /// Constellations this product contains
+    constellations: Vec<Constellation>,
+    /// Epoch [Decimator]
+    decimator: Decimator,
+    /// Current epoch is decimated
+    decimated: bool,
     buf: Observations,
+    /// Elevation [deg] and C/N0 [dB.Hz] per SV and signal code, of the current epoch
+    cno: CnoSamples,
+    /// Epochs (and preceding events) waiting for the header release
+    pending: Vec<(ObsKey, Observations, CnoSamples, Vec<Event>)>,
+    /// Events preceding next epoch
+    events: Vec<Event>,
+    /// Receiver clock reset, at this [Epoch]
+    clock_reset: Option<Epoch>,
+    /// Receiver clock [JumpDetector]
+    jumps: JumpDetector,
+    /// Signal [Sample]s of the current epoch, for the jump detection
+    samples: HashMap<(SV, String), Sample>,
+    /// Loss of lock, to be declared on the next released epoch
+    lock_loss: bool,
+    /// Glonass frequency channels
+    glo_channels: HashMap<SV, i8>,
+    /// Latest [SatelliteState]s, and their [Epoch]
+    satellites: HashMap<SV, (Epoch, SatelliteState)>,
+    /// Observables we tracked, during the learning period
+    learned: HashMap<Constellation, Vec<Observable>>,
+    /// Observables of the current file (header)
+    observables: HashMap<Constellation, Vec<Observable>>,
     header: Option<ObsHeader>,
+    /// Latest receiver clock state: bias [s], drift [s/s]
+    clock: Option<(f64, f64)>,
+    /// Averaged receiver position (ECEF [m])
+    rx_position: Option<(f64, f64, f64)>,
+    /// Number of averaged positions
+    rx_position_count: u32,
+    /// Position is surveyed (or known): no longer averaged
+    rx_position_fixed: bool,
+    /// [QcReport] of current file, when desired
+    qc: Option<QcReport>,
+    /// Offset of the TIME OF LAST OBS record, in current file
+    last_obs_offset: Option<u64>,
     rx: Rx<Message>,
     shutdown: WatchRx<bool>,
     settings: Settings,
@@ -43,11 +109,33 @@ impl Collecter {
         Self {
             rx,
             shutdown,
+            decimator: Decimator::new(settings.sampling_period, ublox.measurement_period),
+            constellations: settings.observables.keys().copied().collect(),
+            observables: settings.observables.clone(),
             settings,
             fd: None,
             t0: None,
             t: None,
+            t_last: None,
+            t_end: None,
+            decimated: false,
             header: None,
+            cno: HashMap::new(),
+            pending: Vec::new(),
+            events: Vec::new(),
+            clock_reset: None,
+            jumps: JumpDetector::default(),
+            samples: HashMap::new(),
+            lock_loss: false,
+            glo_channels: HashMap::new(),
+            satellites: HashMap::new(),
+            learned: HashMap::new(),
+            clock: None,
+            rx_position: None,
+            rx_position_count: 0,
+            rx_position_fixed: false,
+            qc: None,
+            last_obs_offset: None,
             ubx_settings: ublox,
             buf: Observations::default(),
         }
@@ -64,85 +152,158 @@ impl Collecter {
             match self.rx.recv().await {
                 Some(msg) => match msg {
                     Message::Timestamp(t) => {},
-                    Message::FirmwareVersion(version) => {
-                        self.ubx_settings.firmware = Some(version.to_string());
-                    },
 
                     Message::Shutdown => {
-                        if self.buf.signals.len() > 0 || self.buf.clock.is_some() {
-                            self.release_epoch();
-                        }
+                        self.end_epoch();
+
+                        self.rotate();
                         return;
                     },
 
-                    Message::Clock(clock) => {
-                        let bias = clock * 1.0E-3;
-                        let mut clock = ClockObservation::default();
-                        clock.set_offset_s(Default::default(), bias);
-                        self.buf.clock = Some(clock);
+                    Message::FixedPosition(ecef) => {
+                        self.rx_position = Some(ecef);
+                        self.rx_position_fixed = true;
+                    },
+
+                    Message::Position((x, y, z)) => {
+                        if self.rx_position_fixed {
+                            continue;
+                        }
+
+                        let n = self.rx_position_count as f64;
+                        let (x0, y0, z0) = self.rx_position.unwrap_or_default();
+
+                        self.rx_position = Some((
+                            (x0 * n + x) / (n + 1.0),
+                            (y0 * n + y) / (n + 1.0),
+                            (z0 * n + z) / (n + 1.0),
+                        ));
+
+                        self.rx_position_count += 1;
+                    },
+
+                    Message::ClockReset(t) => {
+                        self.clock_reset = Some(t);
+                    },
+
+                    Message::Event(event) => {
+                        self.events.push(event);
+                    },
+
+                    Message::Satellites(sky) => {
+                        self.update_satellites(sky.epoch, &sky.satellites);
+                    },
+
+                    Message::Clock((bias, drift)) => {
+                        if !self.decimated {
+                            let mut clock = ClockObservation::default();
+                            clock.set_offset_s(Default::default(), bias);
+                            self.buf.clock = Some(clock);
+                        }
+                        self.clock = Some((bias, drift));
                     },
 
                     Message::Measurement(rawxm) => {
-                        if self.t0.is_none() {
-                            self.t0 = Some(rawxm.t);
-                            self.release_header();
+                        if !self.constellations.contains(&rawxm.sv.constellation) {
+                            continue;
                         }
 
-                        if self.t.is_none() {
+                        let new_epoch = match self.t {
+                            Some(t) => rawxm.t > t,
+                            None => true,
+                        };
+
+                        if new_epoch {
+                            self.end_epoch();
+
                             self.t = Some(rawxm.t);
+                            self.decimated = !self.decimator.keep(rawxm.t);
                         }
 
-                        let t = self.t.unwrap();
+                        self.collect_sample(&rawxm);
 
-                        if rawxm.t > t {
-                            if self.buf.signals.len() > 0 || self.buf.clock.is_some() {
-                                self.release_epoch();
+                        if self.decimated {
+                            continue;
+                        }
+
+                        if let Some(t_end) = self.t_end {
+                            if rawxm.t >= t_end {
+                                self.rotate();
                             }
                         }
 
-                        let c1c = if self.settings.major == 3 {
-                            Observable::from_str("C1C").unwrap()
-                        } else {
-                            Observable::from_str("C1").unwrap()
-                        };
+                        if self.t0.is_none() {
+                            self.t0 = Some(rawxm.t);
+                            self.t_end =
+                                Some(self.settings.period_start(rawxm.t) + self.settings.period);
 
-                        let l1c = if self.settings.major == 3 {
-                            Observable::from_str("L1C").unwrap()
-                        } else {
-                            Observable::from_str("L1").unwrap()
-                        };
+                            if !self.settings.qc_formats.is_empty() {
+                                self.qc = Some(QcReport::new(self.settings.sampling_period));
+                            }
+                        }
+
+                        if rawxm.sv.constellation == Constellation::Glonass {
+                            self.glo_channels.insert(rawxm.sv, rawxm.glonass_channel());
+                        }
 
-                        let d1c = if self.settings.major == 3 {
-                            Observable::from_str("D1C").unwrap()
-                        } else {
-                            Observable::from_str("D1").unwrap()
+                        if self.below_mask(rawxm.sv, rawxm.t) {
+                            continue;
+                        }
+
+                        let code = match to_signal_code(rawxm.sv.constellation, rawxm.sig_id) {
+                            Some(code) => code,
+                            None => {
+                                debug!(
+                                    "{}({}) - unknown signal #{}",
+                                    rawxm.t, rawxm.sv, rawxm.sig_id
+                                );
+                                continue;
+                            },
                         };
 
-                        self.buf.signals.push(SignalObservation {
-                            sv: rawxm.sv,
-                            lli: None,
-                            snr: None,
-                            value: rawxm.cp,
-                            observable: c1c,
-                        });
-
-                        self.buf.signals.push(SignalObservation {
-                            sv: rawxm.sv,
-                            lli: None,
-                            snr: None,
-                            value: rawxm.pr,
-                            observable: l1c,
-                        });
-
-                        self.buf.signals.push(SignalObservation {
-                            sv: rawxm.sv,
-                            lli: None,
-                            snr: None,
-                            value: rawxm.dop as f64,
-                            observable: d1c,
-                        });
-
-                        self.t = Some(rawxm.t);
+                        if self.qc.is_some() {
+                            if let Some(elev_deg) = self.elevation(rawxm.sv, rawxm.t) {
+                                let code = if self.settings.major == 2 {
+                                    &code[..1]
+                                } else {
+                                    code
+                                };
+
+                                self.cno
+                                    .insert((rawxm.sv, code.to_string()), (elev_deg, rawxm.cno));
+                            }
+                        }
+
+                        let mut cp = rawxm.cp;
+
+                        if self.settings.phase_shift == Some(PhaseShiftMode::Apply) {
+                            if let Some(shift) = phase_shift(rawxm.sv.constellation, code) {
+                                cp += shift;
+                            }
+                        }
+
+                        for (prefix, value) in [('C', rawxm.pr), ('L', cp), ('D', rawxm.dop as f64)]
+                        {
+                            let observable = if self.settings.major == 2 {
+                                format!("{}{}", prefix, &code[..1])
+                            } else {
+                                format!("{}{}", prefix, code)
+                            };
+
+                            let observable = Observable::from_str(&observable).unwrap();
+
+                            if self.settings.learn_observables && self.fd.is_none() {
+                                self.learn(rawxm.sv.constellation, &observable);
+                            }
+
+                            self.buf.signals.push(SignalObservation {
+                                sv: rawxm.sv,
+                                lli: None,
+                                snr: None,
+                                value,
+                                observable,
+                            });
+                        }
                     },
                     _ => {},
                 },
@@ -151,15 +312,109 @@ impl Collecter {
         }
     }
 
+    /// Updates the [SatelliteState]s and declares the health transitions
+    fn update_satellites(&mut self, t: Epoch, satellites: &[SatelliteState]) {
+        for sat in satellites.iter() {
+            if !self.constellations.contains(&sat.sv.constellation) {
+                continue;
+            }
+
+            let previous = self
+                .satellites
+                .insert(sat.sv, (t, *sat))
+                .map(|(_, previous)| previous.health)
+                .unwrap_or_default();
+
+            let comment = match (previous, sat.health) {
+                (SvHealth::Unhealthy, SvHealth::Unhealthy) => continue,
+                (_, SvHealth::Unhealthy) => format!("{} UNHEALTHY", sat.sv),
+                (SvHealth::Unhealthy, SvHealth::Healthy) => format!("{} HEALTHY", sat.sv),
+                _ => continue,
+            };
+
+            warn!("{} - {}", t, comment.to_lowercase());
+            self.events.push(Event::comments(t, &[comment.as_str()]));
+        }
+    }
+
+    /// Returns true if this SV should be removed by the elevation mask, at this [Epoch]
+    fn below_mask(&self, sv: SV, t: Epoch) -> bool {
+        match self.settings.elev_mask_deg {
+            Some(mask) => masked(mask, t, self.elevation_validity(), self.satellites.get(&sv)),
+            None => false,
+        }
+    }
+
+    /// Elevation [deg] of this SV at this [Epoch], if known and still valid
+    fn elevation(&self, sv: SV, t: Epoch) -> Option<i8> {
+        valid_elevation(t, self.elevation_validity(), self.satellites.get(&sv))
+    }
+
+    /// Validity of the reported elevations. NAV-SAT is streamed
+    /// with each navigation solution.
+    fn elevation_validity(&self) -> Duration {
+        let nav_period =
+            self.ubx_settings.measurement_period * self.ubx_settings.solutions_ratio as f64;
+
+        std::cmp::max(
+            nav_period * 2.0,
+            Duration::from_seconds(ELEVATION_VALIDITY_S),
+        )
+    }
+
+    /// Learns a new observable, if its kind was selected
+    fn learn(&mut self, constellation: Constellation, observable: &Observable) {
+        let selected = self
+            .settings
+            .observables
+            .values()
+            .flatten()
+            .any(|selected| {
+                (selected.is_pseudo_range_observable() && observable.is_pseudo_range_observable())
+                    || (selected.is_phase_range_observable()
+                        && observable.is_phase_range_observable())
+                    || (selected.is_doppler_observable() && observable.is_doppler_observable())
+            });
+
+        if !selected {
+            return;
+        }
+
+        let learned = self.learned.entry(constellation).or_default();
+
+        if !learned.contains(observable) {
+            debug!("{} - new observable: {}", constellation, observable);
+            learned.push(observable.clone());
+            learned.sort_by_key(|observable| observable.to_string());
+        }
+    }
+
     fn release_header(&mut self) {
         let t0 = self.t0.unwrap();
 
+        // learned observables are restricted to the selection (see learn()),
+        // which remains untouched for the next files
+        self.observables = if self.settings.learn_observables && !self.learned.is_empty() {
+            self.learned.clone()
+        } else {
+            self.settings.observables.clone()
+        };
+
         // obtain new file, release header
         let mut fd = BufWriter::new(self.fd(t0));
 
         let header = self.build_header();
 
-        header.format(&mut fd).unwrap_or_else(|e| {
+        let mut content = format_header(&header);
+
+        if self.settings.glonass_time {
+            for label in ["TIME OF FIRST OBS", "TIME OF LAST OBS"] {
+                replace_time_system(&mut content, label, "UTC", "GLO");
+            }
+        }
+        insert_records(&mut content, &self.custom_records());
+
+        fd.write_all(&content).unwrap_or_else(|e| {
             panic!(
                 "RINEX header formatting: {}. Aborting (avoiding corrupt file)",
                 e
@@ -168,38 +423,347 @@ impl Collecter {
 
         let _ = fd.flush();
 
+        self.last_obs_offset =
+            find_line(&content, "TIME OF LAST OBS").map(|(offset, _)| offset as u64);
+
         self.fd = Some(fd);
         self.header = Some(header.obs.unwrap().clone());
     }
 
+    /// Releases current file and prepares the next one
+    fn rotate(&mut self) {
+        // learning period is not complete: release anyway
+        if self.fd.is_none() && !self.pending.is_empty() {
+            self.release_header();
+            self.release_pending();
+        }
+
+        self.release_file();
+
+        self.t0 = None;
+        self.t_end = None;
+        self.t_last = None;
+
+        // the next header only describes the next period
+        self.learned.clear();
+        self.glo_channels.clear();
+
+        // the jump detection runs on every received epoch and is not reset:
+        // a jump across files is still detected, and the accumulated jumps
+        // keep the observations continuous from one file to another.
+    }
+
+    /// Releases current file: patches the TIME OF LAST OBS
+    /// record, that we could not know at the time of the header release.
+    fn release_file(&mut self) {
+        let t = match self.t_last {
+            Some(t) => t,
+            None => return,
+        };
+
+        let mut fd = match self.fd.take() {
+            Some(fd) => fd,
+            None => return,
+        };
+
+        let _ = fd.flush();
+
+        if let Some(offset) = self.last_obs_offset.take() {
+            let mut header = self.build_header();
+
+            if let Some(obs) = header.obs.as_mut() {
+                obs.timeof_last_obs = Some(t);
+            }
+
+            let mut content = format_header(&header);
+
+            if self.settings.glonass_time {
+                replace_time_system(&mut content, "TIME OF LAST OBS", "UTC", "GLO");
+            }
+
+            if let Some((line_offset, len)) = find_line(&content, "TIME OF LAST OBS") {
+                let line = &content[line_offset..line_offset + len];
+
+                if let Err(e) = fd.get_mut().patch(offset, line) {
+                    warn!("{} - could not update TIME OF LAST OBS: {}", t, e);
+                }
+            }
+        }
+
+        self.header = None;
+
+        if let Some(t0) = self.t0 {
+            self.release_qc(t0);
+        }
+    }
+
+    /// Releases the [QcReport] of current file, next to it
+    fn release_qc(&mut self, t0: Epoch) {
+        let mut qc = match self.qc.take() {
+            Some(qc) => qc,
+            None => return,
+        };
+
+        qc.finalize();
+
+        let filepath = self.settings.filename(false, t0);
+        let filepath = filepath.strip_suffix(".gz").unwrap_or(&filepath);
+        let filename = filepath.rsplit('/').next().unwrap_or(filepath);
+
+        for format in self.settings.qc_formats.iter() {
+            let path = format!("{}.{}", filepath, format.extension());
+
+            let content = match format {
+                QcFormat::Text => qc.to_text(filename),
+                QcFormat::Json => serde_json::to_string_pretty(&qc.to_json(filename))
+                    .unwrap_or_else(|e| panic!("QC report formatting: {}", e)),
+            };
+
+            match std::fs::write(&path, content) {
+                Ok(_) => debug!("{} - QC report: {}", t0, path),
+                Err(e) => error!("{} - failed to write QC report \"{}\": {}", t0, path, e),
+            }
+        }
+    }
+
+    /// Completes current epoch: runs the clock jump detection on every
+    /// epoch (decimated or not), then releases the epoch if it was kept.
+    fn end_epoch(&mut self) {
+        if let Some(t) = self.t {
+            self.detect_jumps(t);
+        }
+
+        if !self.buf.signals.is_empty() || self.buf.clock.is_some() {
+            self.release_epoch();
+        }
+    }
+
+    /// Detects the receiver clock jumps (or reset) at this [Epoch]
+    fn detect_jumps(&mut self, t: Epoch) {
+        let samples = std::mem::take(&mut self.samples);
+
+        if self.clock_reset.map(|reset| reset <= t).unwrap_or(false) {
+            self.clock_reset = None;
+
+            // clock state and accumulated jumps no longer apply
+            self.clock = None;
+            self.jumps = JumpDetector::default();
+            self.jumps.detect(t, samples);
+
+            // phase ambiguities are lost
+            self.lock_loss = true;
+            self.events
+                .push(Event::comments(t, &["RECEIVER CLOCK RESET"]));
+            return;
+        }
+
+        let (pr_jump, phase_jump) = self.jumps.detect(t, samples);
+
+        if pr_jump != 0 || phase_jump != 0 {
+            warn!(
+                "{} - clock jump: pseudo range {}ms, phase {}ms",
+                t, pr_jump, phase_jump
+            );
+
+            let comment = format!(
+                "RECEIVER CLOCK JUMP: PR {:+} MS, PHASE {:+} MS",
+                pr_jump, phase_jump
+            );
+
+            self.events.push(Event::comments(t, &[comment.as_str()]));
+            self.lock_loss |= phase_jump != 0 && !self.settings.remove_clock_jumps;
+        }
+    }
+
+    /// Collects this measurement, expressed in meters, for the clock jump detection
+    fn collect_sample(&mut self, rawxm: &Rawxm) {
+        let code = match to_signal_code(rawxm.sv.constellation, rawxm.sig_id) {
+            Some(code) => code,
+            None => return,
+        };
+
+        let frequency =
+            match carrier_frequency(rawxm.sv.constellation, code, rawxm.glonass_channel()) {
+                Some(frequency) => frequency,
+                None => return,
+            };
+
+        let wavelength = SPEED_OF_LIGHT_M_S / frequency;
+
+        let sample = Sample {
+            pr: rawxm.pr,
+            phase: Some(rawxm.cp)
+                .filter(|phase| *phase != 0.0)
+                .map(|phase| phase * wavelength),
+            range_rate: -rawxm.dop as f64 * wavelength,
+        };
+
+        self.samples.insert((rawxm.sv, code.to_string()), sample);
+    }
+
     fn release_epoch(&mut self) {
         let t = self.t.unwrap();
 
-        let key = ObsKey {
+        let mut key = ObsKey {
             epoch: t,
             flag: EpochFlag::Ok, // TODO,
         };
 
-        let mut fd = self.fd.as_mut().unwrap();
+        let mut buf = std::mem::take(&mut self.buf);
+        let cno = std::mem::take(&mut self.cno);
+
+        // events that happened after this epoch remain pending
+        let (mut events, later): (Vec<_>, Vec<_>) = std::mem::take(&mut self.events)
+            .into_iter()
+            .partition(|event| event.epoch <= t);
+
+        self.events = later;
+        events.sort_by_key(|event| event.epoch);
+
+        if self.settings.remove_clock_jumps {
+            self.remove_clock_jumps(&mut buf);
+        }
+
+        // loss of lock may have happened on a decimated epoch
+        if std::mem::take(&mut self.lock_loss) {
+            for signal in buf.signals.iter_mut() {
+                if signal.observable.is_phase_range_observable() {
+                    signal.lli = Some(LliFlags::LOCK_LOSS);
+                }
+            }
+        }
+
+        if self.settings.clock_offset_applied {
+            self.apply_clock_offset(&mut key, &mut buf);
+        }
+
+        self.t_last = Some(key.epoch);
+        self.pending.push((key, buf, cno, events));
+
+        if self.fd.is_none() {
+            // header is released after the learning period
+            if self.pending.len() < self.settings.learning_epochs {
+                return;
+            }
+            self.release_header();
+        }
+
+        self.release_pending();
+    }
+
+    /// Removes the accumulated clock jumps, so the pseudo range
+    /// and phase observations remain continuous.
+    fn remove_clock_jumps(&self, buf: &mut Observations) {
+        let pr_jumps_s = self.jumps.pr_jumps_ms as f64 * 1.0E-3;
+        let phase_jumps_s = self.jumps.phase_jumps_ms as f64 * 1.0E-3;
+
+        for signal in buf.signals.iter_mut() {
+            if signal.observable.is_pseudo_range_observable() {
+                signal.value -= SPEED_OF_LIGHT_M_S * pr_jumps_s;
+            } else if signal.observable.is_phase_range_observable() {
+                let code = signal.observable.to_string();
+                let code = code.get(1..).unwrap_or_default();
+
+                let glo_channel = self
+                    .glo_channels
+                    .get(&signal.sv)
+                    .copied()
+                    .unwrap_or_default();
+
+                if let Some(frequency) =
+                    carrier_frequency(signal.sv.constellation, code, glo_channel)
+                {
+                    signal.value -= frequency * phase_jumps_s;
+                }
+            }
+        }
+    }
+
+    /// Removes the receiver clock offset from epoch and observations,
+    /// in a consistent manner.
+    fn apply_clock_offset(&self, key: &mut ObsKey, buf: &mut Observations) {
+        match self.clock {
+            Some(clock) => remove_clock_offset(clock, &self.glo_channels, key, buf),
+            None => warn!("{} - clock state is not known yet", key.epoch),
+        }
+    }
+
+    fn release_pending(&mut self) {
+        let fd = self.fd.as_mut().unwrap();
 
         let header = self
             .header
             .as_ref()
             .expect("internal error: missing Observation header");
 
-        match self
-            .buf
-            .format(self.settings.major == 2, &key, header, &mut fd)
-        {
-            Ok(_) => {
-                let _ = fd.flush();
-                self.buf.clock = None;
-                self.buf.signals.clear();
-            },
-            Err(e) => {
-                error!("{} formatting issue: {}", t, e);
-            },
+        for (key, buf, cno, events) in self.pending.drain(..) {
+            if let Some(qc) = self.qc.as_mut() {
+                // only the declared observables are released
+                let mut released = buf.clone();
+
+                released.signals.retain(|signal| {
+                    header
+                        .codes
+                        .get(&signal.sv.constellation)
+                        .map(|codes| codes.contains(&signal.observable))
+                        .unwrap_or(false)
+                });
+
+                qc.add_epoch(key.epoch, &released, &cno, &self.glo_channels);
+            }
+
+            for event in events.iter() {
+                if let Err(e) = event.format(self.settings.major == 2, fd) {
+                    error!("{} event formatting issue: {}", event.epoch, e);
+                }
+            }
+
+            match buf.format(self.settings.major == 2, &key, header, fd) {
+                Ok(_) => {
+                    let _ = fd.flush();
+                },
+                Err(e) => {
+                    error!("{} formatting issue: {}", key.epoch, e);
+                },
+            }
+        }
+    }
+
+    /// Header records that we format ourselves
+    fn custom_records(&self) -> Vec<String> {
+        let mut records = Vec::new();
+
+        if self.settings.major > 2 && !self.glo_channels.is_empty() {
+            let observables = self
+                .observables
+                .get(&Constellation::Glonass)
+                .map(|observables| observables.as_slice())
+                .unwrap_or_default();
+
+            records.push(glonass_cod_phs_bis_record(observables));
         }
+
+        if self.settings.major > 2 && self.settings.phase_shift.is_some() {
+            let mut constellations = self.observables.keys().collect::<Vec<_>>();
+            constellations.sort_by_key(|constellation| format!("{:x}", constellation));
+
+            for constellation in constellations {
+                for observable in self.observables[constellation].iter() {
+                    if observable.is_phase_range_observable() {
+                        records.push(phase_shift_record(*constellation, &observable.to_string()));
+                    }
+                }
+            }
+        }
+
+        if let Some(mask) = self.settings.elev_mask_deg {
+            records.push(header_record(
+                &format!("ELEVATION CUTOFF ANGLE: {:.1} DEG", mask),
+                "COMMENT",
+            ));
+        }
+
+        records
     }
 
     fn build_header(&self) -> Header {
@@ -222,17 +786,196 @@ impl Collecter {
             obs_header.crinex = Some(crinex);
         }
 
-        if let Some(operator) = &self.settings.operator {
-            header.observer = Some(operator.clone());
+        self.settings.customize_header(&mut header);
+
+        let mut receiver = Receiver::default();
+
+        if let Some(model) = &self.ubx_settings.model {
+            receiver = receiver.with_model(model);
+        }
+
+        if let Some(sn) = &self.ubx_settings.sn {
+            receiver = receiver.with_serial_number(sn);
         }
 
-        if let Some(agency) = &self.settings.agency {
-            header.agency = Some(agency.clone());
+        if let Some(firmware) = &self.ubx_settings.firmware {
+            receiver = receiver.with_firmware(firmware);
         }
 
-        obs_header.codes = self.settings.observables.clone();
+        header.rcvr = Some(receiver);
+        header.rcvr_antenna = self.settings.antenna.clone();
+        header.rx_position = self.rx_position;
+        header.sampling_interval = Some(self.settings.sampling_period);
+        header.glo_channels = self.glo_channels.clone();
+
+        obs_header.codes = self.observables.clone();
+        obs_header.clock_offset_applied = self.settings.clock_offset_applied;
+
+        // TIME OF LAST OBS is patched when the file is released
+        obs_header.timeof_first_obs = self.t0;
+        obs_header.timeof_last_obs = self.t0;
 
         header.obs = Some(obs_header);
         header
     }
 }
+
+/// Returns true if an observation made at this [Epoch] should be removed by this
+/// elevation mask [deg]: SV is below the mask, or its elevation is not known
+/// (or no longer valid, see `validity`).
+fn masked(
+    mask_deg: f64,
+    t: Epoch,
+    validity: Duration,
+    state: Option<&(Epoch, SatelliteState)>,
+) -> bool {
+    match valid_elevation(t, validity, state) {
+        Some(elev_deg) => (elev_deg as f64) < mask_deg,
+        None => true,
+    }
+}
+
+/// Removes this receiver clock bias [s] and drift [s/s] from epoch and observations
+fn remove_clock_offset(
+    (bias, drift): (f64, f64),
+    glo_channels: &HashMap<SV, i8>,
+    key: &mut ObsKey,
+    buf: &mut Observations,
+) {
+    key.epoch -= Duration::from_seconds(bias);
+
+    for signal in buf.signals.iter_mut() {
+        let code = signal.observable.to_string();
+        let code = code.get(1..).unwrap_or_default();
+
+        let glo_channel = glo_channels.get(&signal.sv).copied().unwrap_or_default();
+
+        if signal.observable.is_pseudo_range_observable() {
+            signal.value -= SPEED_OF_LIGHT_M_S * bias;
+        } else if let Some(frequency) =
+            carrier_frequency(signal.sv.constellation, code, glo_channel)
+        {
+            if signal.observable.is_phase_range_observable() {
+                signal.value -= frequency * bias;
+            } else if signal.observable.is_doppler_observable() {
+                signal.value += frequency * drift;
+            }
+        }
+    }
+}
+
+/// Returns the elevation [deg] of this [SatelliteState], if it was
+/// reported within `validity` of this [Epoch].
+fn valid_elevation(
+    t: Epoch,
+    validity: Duration,
+    state: Option<&(Epoch, SatelliteState)>,
+) -> Option<i8> {
+    let (t_sat, sat) = state?;
+
+    if (t - *t_sat).abs() <= validity {
+        Some(sat.elev_deg)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{masked, remove_clock_offset};
+    use crate::{
+        collecter::skyplot::{SatelliteState, SvHealth},
+        ubx::{nav_clock_state, ubx_frame},
+        utils::SPEED_OF_LIGHT_M_S,
+    };
+    use hifitime::prelude::{Duration, Epoch};
+    use rinex::prelude::{
+        obs::{EpochFlag, ObsKey, Observations, SignalObservation},
+        Observable, SV,
+    };
+    use std::{collections::HashMap, str::FromStr};
+    use ublox::{PacketRef, Parser};
+
+    #[test]
+    fn test_nav_clock_offset() {
+        // iTOW, clkB=123456 ns, clkD=-25 ns/s, tAcc=20 ns, fAcc=500 ps/s
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        payload.extend_from_slice(&123456i32.to_le_bytes());
+        payload.extend_from_slice(&(-25i32).to_le_bytes());
+        payload.extend_from_slice(&20u32.to_le_bytes());
+        payload.extend_from_slice(&500u32.to_le_bytes());
+
+        let frame = ubx_frame(0x01, 0x22, &payload);
+
+        let mut parser = Parser::default();
+        let mut it = parser.consume_ubx(&frame);
+
+        let (bias, drift) = match it.next() {
+            Some(Ok(PacketRef::NavClock(pkt))) => nav_clock_state(&pkt),
+            _ => panic!("NAV-CLOCK parsing failed"),
+        };
+
+        assert!((bias - 123.456E-6).abs() < 1.0E-15, "bias {}", bias);
+        assert!((drift + 25.0E-9).abs() < 1.0E-18, "drift {}", drift);
+
+        let t = Epoch::from_str("2025-02-25T12:00:00 GPST").unwrap();
+
+        let mut key = ObsKey {
+            epoch: t,
+            flag: EpochFlag::Ok,
+        };
+
+        let mut buf = Observations::default();
+
+        buf.signals.push(SignalObservation {
+            sv: SV::from_str("G05").unwrap(),
+            lli: None,
+            snr: None,
+            value: 20.0E6,
+            observable: Observable::from_str("C1C").unwrap(),
+        });
+
+        remove_clock_offset((bias, drift), &HashMap::new(), &mut key, &mut buf);
+
+        assert_eq!(key.epoch, t - Duration::from_nanoseconds(123456.0));
+
+        // 123.456 us: about 37 km
+        let correction = 20.0E6 - buf.signals[0].value;
+        assert!((correction - SPEED_OF_LIGHT_M_S * 123.456E-6).abs() < 1.0E-6);
+        assert!((correction - 37011.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_elevation_mask() {
+        let t = Epoch::from_str("2025-02-25T12:00:00 GPST").unwrap();
+        let validity = Duration::from_seconds(30.0);
+
+        let state = |elev_deg: i8| SatelliteState {
+            sv: SV::from_str("G05").unwrap(),
+            elev_deg,
+            azim_deg: 270,
+            cno_dbhz: 42,
+            pr_res_m: 0.0,
+            used: true,
+            health: SvHealth::Healthy,
+            eph_available: true,
+        };
+
+        // elevation is not known yet
+        assert!(masked(10.0, t, validity, None));
+
+        // below, at and above the mask
+        assert!(masked(10.0, t, validity, Some(&(t, state(9)))));
+        assert!(!masked(10.0, t, validity, Some(&(t, state(10)))));
+        assert!(!masked(10.0, t, validity, Some(&(t, state(45)))));
+
+        // recent enough
+        let t_sat = t - Duration::from_seconds(30.0);
+        assert!(!masked(10.0, t, validity, Some(&(t_sat, state(45)))));
+
+        // outdated
+        let t_sat = t - Duration::from_seconds(31.0);
+        assert!(masked(10.0, t, validity, Some(&(t_sat, state(45)))));
+    }
+}
@@ -0,0 +1,64 @@
+use rinex::prelude::Constellation;
+
+use crate::collecter::header::header_record;
+
+/// How we handle the quarter-cycle phase shifts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhaseShiftMode {
+    /// Phase shifts are applied to the phase observations,
+    /// and declared in the header
+    Apply,
+    /// Phase shifts are only declared in the header
+    Declare,
+}
+
+impl std::str::FromStr for PhaseShiftMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "apply" => Ok(Self::Apply),
+            "declare" => Ok(Self::Declare),
+            _ => Err(format!("invalid phase shift mode \"{}\"", s)),
+        }
+    }
+}
+
+/// Returns the phase shift (in cycles) that aligns this signal
+/// to the reference signal of its frequency band (RINEX V3.04, table A23).
+pub fn phase_shift(constellation: Constellation, code: &str) -> Option<f64> {
+    match constellation {
+        Constellation::GPS | Constellation::QZSS => match code {
+            "2S" | "2L" | "2X" => Some(-0.25),
+            "5Q" => Some(-0.25),
+            _ => None,
+        },
+        Constellation::Galileo => match code {
+            "1C" => Some(0.5),
+            "5Q" | "7Q" => Some(-0.25),
+            _ => None,
+        },
+        Constellation::BeiDou => match code {
+            "2Q" | "7Q" => Some(-0.25),
+            _ => None,
+        },
+        Constellation::Glonass => match code {
+            "1P" | "2P" => Some(0.25),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Formats one SYS / PHASE SHIFT record, for given phase observable (like "L2L").
+/// Observables that do not require a correction have a blank value.
+pub fn phase_shift_record(constellation: Constellation, observable: &str) -> String {
+    let code = observable.get(1..).unwrap_or_default();
+
+    let content = match phase_shift(constellation, code) {
+        Some(shift) => format!("{:x} {:<3} {:8.5}", constellation, observable, shift),
+        None => format!("{:x} {:<3}", constellation, observable),
+    };
+
+    header_record(&content, "SYS / PHASE SHIFT")
+}
@@ -0,0 +1,286 @@
+use std::{io::Write, str::FromStr};
+
+use rinex::prelude::{Epoch, TimeScale};
+
+use crate::collecter::{
+    dataset::{Collecter as DatasetCollecter, DatasetFormat, DatasetRecord},
+    Message,
+};
+
+/// Supported PVT log formats
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PvtFormat {
+    /// Comma separated values
+    Csv,
+    /// NMEA GGA sentences
+    Gga,
+    /// GPX track
+    Gpx,
+}
+
+impl FromStr for PvtFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "gga" | "nmea" => Ok(Self::Gga),
+            "gpx" => Ok(Self::Gpx),
+            _ => Err(format!("unknown PVT format \"{}\"", s)),
+        }
+    }
+}
+
+impl DatasetFormat for PvtFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "pvt.csv",
+            Self::Gga => "pvt.nmea",
+            Self::Gpx => "pvt.gpx",
+        }
+    }
+
+    fn header(&self) -> &'static str {
+        match self {
+            Self::Csv => "epoch,fix,carrier,nsv,lat_ddeg,long_ddeg,alt_m,h_acc_m,v_acc_m,pdop,x_ecef_m,y_ecef_m,z_ecef_m\n",
+            Self::Gga => "",
+            Self::Gpx => "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<gpx version=\"1.1\" creator=\"ubx2rinex\">\n<trk>\n<trkseg>\n",
+        }
+    }
+
+    fn footer(&self) -> &'static str {
+        match self {
+            Self::Gpx => "</trkseg>\n</trk>\n</gpx>\n",
+            _ => "",
+        }
+    }
+}
+
+/// Carrier phase solution status
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum CarrierSolution {
+    #[default]
+    None,
+    Float,
+    Fixed,
+}
+
+impl std::fmt::Display for CarrierSolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Float => write!(f, "float"),
+            Self::Fixed => write!(f, "fixed"),
+        }
+    }
+}
+
+/// Navigation solution, as reported by NAV-PVT
+#[derive(Debug, Clone, Default)]
+pub struct Pvt {
+    /// [Epoch] of this solution, in UTC
+    pub epoch: Epoch,
+    /// Fix type: 0 (no fix) to 5 (time only)
+    pub fix_type: u8,
+    /// Fix is valid
+    pub fix_ok: bool,
+    /// Differential corrections were applied
+    pub differential: bool,
+    /// [CarrierSolution] status
+    pub carrier: CarrierSolution,
+    /// Number of SV used in the solution
+    pub num_sv: u8,
+    /// Latitude [ddeg]
+    pub latitude: f64,
+    /// Longitude [ddeg]
+    pub longitude: f64,
+    /// Height above ellipsoid [m]
+    pub height: f64,
+    /// Height above mean sea level [m]
+    pub height_msl: f64,
+    /// Horizontal accuracy estimate [m]
+    pub h_acc: f64,
+    /// Vertical accuracy estimate [m]
+    pub v_acc: f64,
+    /// Position DOP
+    pub pdop: f64,
+    /// ECEF coordinates [m]
+    pub ecef: (f64, f64, f64),
+}
+
+impl DatasetRecord for Pvt {
+    type Format = PvtFormat;
+
+    const NAME: &'static str = "PVT log";
+
+    fn from_message(msg: Message) -> Option<Self> {
+        match msg {
+            Message::Pvt(pvt) => Some(pvt),
+            _ => None,
+        }
+    }
+
+    fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// Formats this [Pvt] solution
+    fn format<W: Write>(&self, format: PvtFormat, w: &mut W) -> std::io::Result<()> {
+        match format {
+            PvtFormat::Csv => writeln!(
+                w,
+                "{},{},{},{},{:.9},{:.9},{:.3},{:.3},{:.3},{:.2},{:.3},{:.3},{:.3}",
+                self.epoch,
+                self.fix_type,
+                self.carrier,
+                self.num_sv,
+                self.latitude,
+                self.longitude,
+                self.height,
+                self.h_acc,
+                self.v_acc,
+                self.pdop,
+                self.ecef.0,
+                self.ecef.1,
+                self.ecef.2,
+            ),
+            PvtFormat::Gga => writeln!(w, "{}", self.gga()),
+            PvtFormat::Gpx => {
+                let (y, m, d, hh, mm, ss, nanos) = self.epoch.to_gregorian(TimeScale::UTC);
+                writeln!(
+                    w,
+                    "<trkpt lat=\"{:.9}\" lon=\"{:.9}\"><ele>{:.3}</ele><time>{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z</time><sat>{}</sat><pdop>{:.2}</pdop></trkpt>",
+                    self.latitude,
+                    self.longitude,
+                    self.height_msl,
+                    y,
+                    m,
+                    d,
+                    hh,
+                    mm,
+                    ss,
+                    nanos / 1_000_000,
+                    self.num_sv,
+                    self.pdop,
+                )
+            },
+        }
+    }
+}
+
+impl Pvt {
+    /// GGA quality indicator
+    fn gga_quality(&self) -> u8 {
+        if !self.fix_ok || self.fix_type == 0 || self.fix_type == 5 {
+            0
+        } else if self.carrier == CarrierSolution::Fixed {
+            4
+        } else if self.carrier == CarrierSolution::Float {
+            5
+        } else if self.fix_type == 1 {
+            6
+        } else if self.differential {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Formats this [Pvt] solution as NMEA GGA sentence
+    fn gga(&self) -> String {
+        let (_, _, _, hh, mm, ss, nanos) = self.epoch.to_gregorian(TimeScale::UTC);
+
+        let (lat, lat_hemi) = if self.latitude < 0.0 {
+            (-self.latitude, 'S')
+        } else {
+            (self.latitude, 'N')
+        };
+
+        let (long, long_hemi) = if self.longitude < 0.0 {
+            (-self.longitude, 'W')
+        } else {
+            (self.longitude, 'E')
+        };
+
+        let lat = nmea_angle(lat, 2);
+        let long = nmea_angle(long, 3);
+
+        // HDOP is not reported by NAV-PVT
+        let body = format!(
+            "GPGGA,{:02}{:02}{:02}.{:02},{},{},{},{},{},{:02},,{:.3},M,{:.3},M,,",
+            hh,
+            mm,
+            ss,
+            nanos / 10_000_000,
+            lat,
+            lat_hemi,
+            long,
+            long_hemi,
+            self.gga_quality(),
+            self.num_sv,
+            self.height_msl,
+            self.height - self.height_msl,
+        );
+
+        let checksum = body.bytes().fold(0u8, |cs, b| cs ^ b);
+        format!("${}*{:02X}", body, checksum)
+    }
+}
+
+/// Formats positive decimal degrees as NMEA (d)ddmm.mmmmm angle.
+/// Minutes are rounded first, so 59.999999' carries into the degrees.
+fn nmea_angle(ddeg: f64, deg_digits: usize) -> String {
+    // 1E-5 minute units
+    let total = (ddeg * 60.0E5).round() as u64;
+    let (deg, minutes) = (total / 6_000_000, total % 6_000_000);
+
+    format!(
+        "{:0width$}{:02}.{:05}",
+        deg,
+        minutes / 100_000,
+        minutes % 100_000,
+        width = deg_digits
+    )
+}
+
+/// PVT log [Collecter]
+pub type Collecter = DatasetCollecter<Pvt>;
+
+#[cfg(test)]
+mod test {
+    use super::{nmea_angle, CarrierSolution, Pvt};
+    use hifitime::prelude::Epoch;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_gga() {
+        let pvt = Pvt {
+            epoch: Epoch::from_str("2025-02-25T12:35:19 UTC").unwrap(),
+            fix_type: 3,
+            fix_ok: true,
+            carrier: CarrierSolution::None,
+            num_sv: 8,
+            latitude: 48.1173,
+            longitude: 11.516667,
+            height: 594.4,
+            height_msl: 545.4,
+            ..Default::default()
+        };
+
+        let gga = pvt.gga();
+
+        assert!(gga.starts_with(
+            "$GPGGA,123519.00,4807.03800,N,01131.00002,E,1,08,,545.400,M,49.000,M,,*"
+        ));
+    }
+
+    #[test]
+    fn test_nmea_angle() {
+        assert_eq!(nmea_angle(48.1173, 2), "4807.03800");
+        assert_eq!(nmea_angle(11.516667, 3), "01131.00002");
+
+        // 48°59.9999994' rounds up to 49°00.00000'
+        assert_eq!(nmea_angle(48.99999999, 2), "4900.00000");
+        assert_eq!(nmea_angle(179.99999999, 3), "18000.00000");
+    }
+}
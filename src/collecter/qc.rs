@@ -0,0 +1,666 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write as _,
+    str::FromStr,
+};
+
+use rinex::{
+    observation::LliFlags,
+    prelude::{obs::Observations, Duration, Epoch, SV},
+};
+
+use crate::utils::{carrier_frequency, SPEED_OF_LIGHT_M_S};
+
+/// Epoch intervals above this ratio of the sampling period are data gaps
+const GAP_RATIO: f64 = 1.5;
+
+/// Geometry free phase rate that is declared as a cycle slip [m/s].
+/// Same as teqc default (400 cm/min).
+const SLIP_RATE_M_S: f64 = 4.0 / 60.0;
+
+/// Minimal number of samples of one continuous arc, for multipath estimates
+const MIN_ARC_LEN: usize = 10;
+
+/// Width of the C/N0 elevation bins [deg]
+const ELEV_BIN_DEG: i8 = 10;
+
+/// Elevation [deg] and C/N0 [dB.Hz] of one epoch, per SV and signal code
+pub type CnoSamples = HashMap<(SV, String), (i8, u8)>;
+
+/// Supported QC report formats
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum QcFormat {
+    /// Human readable summary
+    Text,
+    /// JSON summary
+    Json,
+}
+
+impl FromStr for QcFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "txt" | "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown QC format \"{}\"", s)),
+        }
+    }
+}
+
+impl QcFormat {
+    /// File extension, appended to the RINEX file name
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Text => "qc.txt",
+            Self::Json => "qc.json",
+        }
+    }
+}
+
+/// Root mean square accumulator
+#[derive(Debug, Copy, Clone, Default)]
+struct Rms {
+    sum_sq: f64,
+    n: usize,
+}
+
+impl Rms {
+    fn rms(&self) -> Option<f64> {
+        if self.n > 0 {
+            Some((self.sum_sq / self.n as f64).sqrt())
+        } else {
+            None
+        }
+    }
+
+    /// Accumulates the deviations of this arc, from its own mean value
+    fn add_arc(&mut self, arc: &[f64]) {
+        if arc.len() < MIN_ARC_LEN {
+            return;
+        }
+
+        let mean = arc.iter().sum::<f64>() / arc.len() as f64;
+
+        for value in arc.iter() {
+            self.sum_sq += (value - mean).powi(2);
+        }
+
+        self.n += arc.len();
+    }
+
+    fn merge(&mut self, rhs: &Self) {
+        self.sum_sq += rhs.sum_sq;
+        self.n += rhs.n;
+    }
+}
+
+/// Observations of one signal, in one epoch
+#[derive(Debug, Copy, Clone, Default)]
+struct Tracking {
+    /// Pseudo range [m]
+    pr: Option<f64>,
+    /// Phase [cycles]
+    phase: Option<f64>,
+    /// Loss of lock was declared
+    lock_loss: bool,
+}
+
+/// Statistics of one signal
+#[derive(Debug, Copy, Clone, Default)]
+struct SignalStats {
+    epochs: u32,
+    first: Option<Epoch>,
+    last: Option<Epoch>,
+}
+
+/// Statistics of one SV
+#[derive(Debug, Clone, Default)]
+struct SvStats {
+    slips: u32,
+    /// Last [Epoch] this SV was observed
+    last: Option<Epoch>,
+    /// Latest geometry free phase combination [m],
+    /// and the signal codes it combines
+    gf: Option<((String, String), f64)>,
+    /// Current multipath arcs [m]
+    mp1_arc: Vec<f64>,
+    mp2_arc: Vec<f64>,
+    mp1: Rms,
+    mp2: Rms,
+}
+
+impl SvStats {
+    /// Closes the current continuous arc
+    fn close_arc(&mut self) {
+        self.mp1.add_arc(&self.mp1_arc);
+        self.mp2.add_arc(&self.mp2_arc);
+        self.mp1_arc.clear();
+        self.mp2_arc.clear();
+        self.gf = None;
+    }
+}
+
+/// Quality control of one Observation RINEX file
+#[derive(Debug, Clone)]
+pub struct QcReport {
+    /// Sampling period of the file
+    sampling_period: Duration,
+    first: Option<Epoch>,
+    last: Option<Epoch>,
+    epochs: u32,
+    /// Data gaps: last epoch before the gap, and gap duration
+    gaps: Vec<(Epoch, Duration)>,
+    /// Statistics per SV and signal code
+    signals: BTreeMap<(SV, String), SignalStats>,
+    svs: BTreeMap<SV, SvStats>,
+    /// C/N0 sum [dB.Hz] and number of samples, per elevation bin
+    cno: BTreeMap<i8, (f64, u32)>,
+}
+
+impl QcReport {
+    /// Builds new [QcReport]
+    pub fn new(sampling_period: Duration) -> Self {
+        Self {
+            sampling_period,
+            first: None,
+            last: None,
+            epochs: 0,
+            gaps: Vec::new(),
+            signals: BTreeMap::new(),
+            svs: BTreeMap::new(),
+            cno: BTreeMap::new(),
+        }
+    }
+
+    /// Accumulates one C/N0 sample, observed at this elevation
+    fn add_cno(&mut self, elev_deg: i8, cno_dbhz: u8) {
+        let bin = elev_deg.clamp(0, 89) / ELEV_BIN_DEG * ELEV_BIN_DEG;
+        let entry = self.cno.entry(bin).or_default();
+        entry.0 += cno_dbhz as f64;
+        entry.1 += 1;
+    }
+
+    /// Analyzes one epoch, as released in the file, and the C/N0
+    /// of its signals.
+    pub fn add_epoch(
+        &mut self,
+        t: Epoch,
+        buf: &Observations,
+        cno: &CnoSamples,
+        glo_channels: &HashMap<SV, i8>,
+    ) {
+        let gap_threshold = self.sampling_period * GAP_RATIO;
+
+        if let Some(last) = self.last {
+            let dt = t - last;
+
+            if dt > gap_threshold {
+                self.gaps.push((last, dt));
+            }
+        }
+
+        if self.first.is_none() {
+            self.first = Some(t);
+        }
+
+        self.last = Some(t);
+        self.epochs += 1;
+
+        let mut signals = BTreeMap::<SV, BTreeMap<String, Tracking>>::new();
+
+        for signal in buf.signals.iter() {
+            let observable = signal.observable.to_string();
+            let code = observable.get(1..).unwrap_or_default().to_string();
+
+            let entry = signals
+                .entry(signal.sv)
+                .or_default()
+                .entry(code)
+                .or_default();
+
+            if signal.observable.is_pseudo_range_observable() {
+                entry.pr = Some(signal.value);
+            } else if signal.observable.is_phase_range_observable() {
+                entry.phase = Some(signal.value).filter(|phase| *phase != 0.0);
+
+                if let Some(lli) = signal.lli {
+                    entry.lock_loss |= lli.intersects(LliFlags::LOCK_LOSS);
+                }
+            }
+        }
+
+        for (sv, codes) in signals {
+            for code in codes.keys() {
+                if let Some((elev_deg, cno_dbhz)) = cno.get(&(sv, code.clone())) {
+                    self.add_cno(*elev_deg, *cno_dbhz);
+                }
+
+                let stats = self.signals.entry((sv, code.clone())).or_default();
+                stats.epochs += 1;
+                stats.last = Some(t);
+
+                if stats.first.is_none() {
+                    stats.first = Some(t);
+                }
+            }
+
+            let glo_channel = glo_channels.get(&sv).copied().unwrap_or_default();
+            let stats = self.svs.entry(sv).or_default();
+
+            let previous = stats.last.replace(t);
+
+            let continuous = previous
+                .map(|previous| t - previous <= gap_threshold)
+                .unwrap_or(false);
+
+            if !continuous {
+                stats.close_arc();
+            }
+
+            if codes.values().any(|tracking| tracking.lock_loss) {
+                stats.slips += 1;
+                stats.close_arc();
+            }
+
+            // dual frequency: widest frequency separation
+            let mut carriers = codes
+                .iter()
+                .filter_map(|(code, tracking)| {
+                    let frequency = carrier_frequency(sv.constellation, code, glo_channel)?;
+                    let phase_m = tracking.phase? * SPEED_OF_LIGHT_M_S / frequency;
+                    Some((code, frequency, tracking.pr?, phase_m))
+                })
+                .collect::<Vec<_>>();
+
+            carriers.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            let ((c1, f1, p1, l1), (c2, f2, p2, l2)) = match (carriers.first(), carriers.last()) {
+                (Some(c1), Some(c2)) if c1.1 / c2.1 > 1.1 => (*c1, *c2),
+                _ => continue,
+            };
+
+            let gf = l1 - l2;
+            let pair = (c1.clone(), c2.clone());
+
+            let gf_prev = match stats.gf.take() {
+                Some((gf_pair, gf_prev)) if gf_pair == pair => Some(gf_prev),
+                Some(_) => {
+                    // other frequency pair: combinations are not comparable
+                    stats.close_arc();
+                    None
+                },
+                None => None,
+            };
+
+            if let (Some(gf_prev), Some(previous)) = (gf_prev, previous.filter(|_| continuous)) {
+                let rate = (gf - gf_prev).abs() / (t - previous).to_seconds();
+
+                if rate > SLIP_RATE_M_S {
+                    stats.slips += 1;
+                    stats.close_arc();
+                }
+            }
+
+            stats.gf = Some((pair, gf));
+
+            let alpha = (f1 / f2).powi(2);
+
+            let mp1 = p1 - (1.0 + 2.0 / (alpha - 1.0)) * l1 + (2.0 / (alpha - 1.0)) * l2;
+
+            let mp2 =
+                p2 - (2.0 * alpha / (alpha - 1.0)) * l1 + (2.0 * alpha / (alpha - 1.0) - 1.0) * l2;
+
+            stats.mp1_arc.push(mp1);
+            stats.mp2_arc.push(mp2);
+        }
+    }
+
+    /// Closes all arcs, once the file is complete
+    pub fn finalize(&mut self) {
+        for stats in self.svs.values_mut() {
+            stats.close_arc();
+        }
+    }
+
+    /// Expected number of epochs, within this time frame
+    fn expected_epochs(&self, first: Epoch, last: Epoch) -> u32 {
+        ((last - first).to_seconds() / self.sampling_period.to_seconds()).round() as u32 + 1
+    }
+
+    /// Signal completeness [%], within its own tracking period
+    fn completeness(&self, stats: &SignalStats) -> (u32, f64) {
+        let expected = match (stats.first, stats.last) {
+            (Some(first), Some(last)) => self.expected_epochs(first, last),
+            _ => 0,
+        };
+
+        let completeness = if expected > 0 {
+            stats.epochs as f64 * 100.0 / expected as f64
+        } else {
+            0.0
+        };
+
+        (expected, completeness)
+    }
+
+    /// Multipath RMS [m], all SV
+    fn mp_rms(&self) -> (Option<f64>, Option<f64>) {
+        let (mut mp1, mut mp2) = (Rms::default(), Rms::default());
+
+        for stats in self.svs.values() {
+            mp1.merge(&stats.mp1);
+            mp2.merge(&stats.mp2);
+        }
+
+        (mp1.rms(), mp2.rms())
+    }
+
+    /// Formats this [QcReport] as human readable summary
+    pub fn to_text(&self, filename: &str) -> String {
+        let mut text = String::new();
+
+        let expected = match (self.first, self.last) {
+            (Some(first), Some(last)) => self.expected_epochs(first, last),
+            _ => 0,
+        };
+
+        let slips = self.svs.values().map(|stats| stats.slips).sum::<u32>();
+        let (mp1, mp2) = self.mp_rms();
+
+        let _ = writeln!(text, "QC REPORT: {}", filename);
+
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            let _ = writeln!(text, "TIME OF FIRST OBS: {}", first);
+            let _ = writeln!(text, "TIME OF LAST OBS:  {}", last);
+        }
+
+        let _ = writeln!(text, "SAMPLING PERIOD:   {}", self.sampling_period);
+        let _ = writeln!(
+            text,
+            "EPOCHS:            {} (expected {})",
+            self.epochs, expected
+        );
+        let _ = writeln!(text, "DATA GAPS:         {}", self.gaps.len());
+        let _ = writeln!(text, "CYCLE SLIPS:       {}", slips);
+        let _ = writeln!(text, "MP1 RMS [m]:       {}", format_opt(mp1));
+        let _ = writeln!(text, "MP2 RMS [m]:       {}", format_opt(mp2));
+
+        if !self.gaps.is_empty() {
+            let _ = writeln!(text, "\nGAP START                         DURATION");
+
+            for (t, dt) in self.gaps.iter() {
+                let _ = writeln!(text, "{:<33} {}", t.to_string(), dt);
+            }
+        }
+
+        let _ = writeln!(text, "\nSV   SIGNAL  EPOCHS  EXPECTED  COMPLETENESS[%]");
+
+        for ((sv, code), stats) in self.signals.iter() {
+            let (expected, completeness) = self.completeness(stats);
+
+            let _ = writeln!(
+                text,
+                "{:<4} {:<7} {:>6}  {:>8}  {:>15.1}",
+                sv.to_string(),
+                code,
+                stats.epochs,
+                expected,
+                completeness
+            );
+        }
+
+        let _ = writeln!(text, "\nSV   SLIPS  MP1[m]  MP2[m]");
+
+        for (sv, stats) in self.svs.iter() {
+            let _ = writeln!(
+                text,
+                "{:<4} {:>5}  {:>6}  {:>6}",
+                sv.to_string(),
+                stats.slips,
+                format_opt(stats.mp1.rms()),
+                format_opt(stats.mp2.rms())
+            );
+        }
+
+        let _ = writeln!(text, "\nELEV[deg]  C/N0[dB.Hz]  SAMPLES");
+
+        for (bin, (sum, n)) in self.cno.iter() {
+            let _ = writeln!(
+                text,
+                "{:02}-{:02}      {:>11.1}  {:>7}",
+                bin,
+                bin + ELEV_BIN_DEG,
+                sum / *n as f64,
+                n
+            );
+        }
+
+        text
+    }
+
+    /// Formats this [QcReport] as JSON summary
+    pub fn to_json(&self, filename: &str) -> serde_json::Value {
+        let expected = match (self.first, self.last) {
+            (Some(first), Some(last)) => self.expected_epochs(first, last),
+            _ => 0,
+        };
+
+        let (mp1, mp2) = self.mp_rms();
+
+        let gaps = self
+            .gaps
+            .iter()
+            .map(|(t, dt)| {
+                serde_json::json!({
+                    "start": t.to_string(),
+                    "duration_s": dt.to_seconds(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let signals = self
+            .signals
+            .iter()
+            .map(|((sv, code), stats)| {
+                let (expected, completeness) = self.completeness(stats);
+
+                serde_json::json!({
+                    "sv": sv.to_string(),
+                    "signal": code,
+                    "epochs": stats.epochs,
+                    "expected": expected,
+                    "completeness_percent": completeness,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let svs = self
+            .svs
+            .iter()
+            .map(|(sv, stats)| {
+                serde_json::json!({
+                    "sv": sv.to_string(),
+                    "cycle_slips": stats.slips,
+                    "mp1_rms_m": stats.mp1.rms(),
+                    "mp2_rms_m": stats.mp2.rms(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let cno = self
+            .cno
+            .iter()
+            .map(|(bin, (sum, n))| {
+                serde_json::json!({
+                    "elev_min_deg": bin,
+                    "elev_max_deg": bin + ELEV_BIN_DEG,
+                    "mean_cno_dbhz": sum / *n as f64,
+                    "samples": n,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "file": filename,
+            "first_epoch": self.first.map(|t| t.to_string()),
+            "last_epoch": self.last.map(|t| t.to_string()),
+            "sampling_period_s": self.sampling_period.to_seconds(),
+            "epochs": self.epochs,
+            "expected_epochs": expected,
+            "gaps": gaps,
+            "cycle_slips": self.svs.values().map(|stats| stats.slips).sum::<u32>(),
+            "mp1_rms_m": mp1,
+            "mp2_rms_m": mp2,
+            "signals": signals,
+            "satellites": svs,
+            "cno_vs_elevation": cno,
+        })
+    }
+}
+
+fn format_opt(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.3}", value),
+        None => "-".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CnoSamples, QcReport};
+    use hifitime::prelude::{Duration, Epoch};
+    use rinex::prelude::{
+        obs::{Observations, SignalObservation},
+        Observable, SV,
+    };
+    use std::{collections::HashMap, str::FromStr};
+
+    fn epoch(sv: SV, values: &[(&str, f64)]) -> Observations {
+        let mut buf = Observations::default();
+
+        for (observable, value) in values.iter() {
+            buf.signals.push(SignalObservation {
+                sv,
+                lli: None,
+                snr: None,
+                value: *value,
+                observable: Observable::from_str(observable).unwrap(),
+            });
+        }
+
+        buf
+    }
+
+    #[test]
+    fn test_qc_frequency_pairs() {
+        let g01 = SV::from_str("G01").unwrap();
+        let t0 = Epoch::from_str("2025-02-25T00:00:00 GPST").unwrap();
+        let dt = Duration::from_seconds(30.0);
+
+        let mut qc = QcReport::new(dt);
+        let glo_channels = HashMap::new();
+
+        let (lambda1, lambda2, lambda5) = (
+            299792458.0 / 1575.42E6,
+            299792458.0 / 1227.60E6,
+            299792458.0 / 1176.45E6,
+        );
+
+        for i in 0..20 {
+            let range = 20.0E6 + i as f64 * 100.0;
+
+            let mut values = vec![
+                ("C1C", range),
+                ("L1C", range / lambda1),
+                ("C5Q", range),
+                ("L5Q", range / lambda5),
+            ];
+
+            // L2 is missing for one epoch: L1/L5 is combined instead.
+            // Each carrier has its own ambiguity, so the combinations differ.
+            if i != 10 {
+                values.push(("C2W", range));
+                values.push(("L2W", range / lambda2 + 1000.0));
+            }
+
+            let buf = epoch(g01, &values);
+            qc.add_epoch(t0 + dt * i as f64, &buf, &CnoSamples::new(), &glo_channels);
+        }
+
+        qc.finalize();
+
+        assert_eq!(qc.svs[&g01].slips, 0);
+    }
+
+    #[test]
+    fn test_qc_report() {
+        let g01 = SV::from_str("G01").unwrap();
+        let t0 = Epoch::from_str("2025-02-25T00:00:00 GPST").unwrap();
+        let dt = Duration::from_seconds(30.0);
+
+        let mut qc = QcReport::new(dt);
+        let glo_channels = HashMap::new();
+
+        // C/N0 of a signal that is not released is not accumulated
+        let cno = CnoSamples::from([
+            ((g01, "1C".to_string()), (45, 40)),
+            ((g01, "2W".to_string()), (5, 30)),
+            ((g01, "5Q".to_string()), (47, 44)),
+        ]);
+
+        let (lambda1, lambda2) = (299792458.0 / 1575.42E6, 299792458.0 / 1227.60E6);
+
+        for i in 0..40 {
+            // one missing epoch
+            if i == 5 {
+                continue;
+            }
+
+            let range = 20.0E6 + i as f64 * 100.0;
+
+            // cycle slip on L1
+            let slip = if i >= 30 { 20.0 } else { 0.0 };
+
+            let buf = epoch(
+                g01,
+                &[
+                    ("C1C", range),
+                    ("L1C", range / lambda1 + slip),
+                    ("C2W", range),
+                    ("L2W", range / lambda2),
+                ],
+            );
+
+            qc.add_epoch(t0 + dt * i as f64, &buf, &cno, &glo_channels);
+        }
+
+        qc.finalize();
+
+        assert_eq!(qc.epochs, 39);
+        assert_eq!(qc.gaps.len(), 1);
+        assert_eq!(qc.gaps[0].1, dt * 2.0);
+
+        let stats = &qc.svs[&g01];
+        assert_eq!(stats.slips, 1);
+
+        // noise free: no multipath
+        let mp1 = stats.mp1.rms().unwrap();
+        assert!(mp1 < 1.0E-3, "mp1 rms {}", mp1);
+
+        let (expected, completeness) = qc.completeness(&qc.signals[&(g01, "1C".to_string())]);
+        assert_eq!(expected, 40);
+        assert!((completeness - 97.5).abs() < 1.0E-6);
+
+        assert_eq!(qc.cno.len(), 2);
+        assert_eq!(qc.cno[&40], (40.0 * 39.0, 39));
+        assert_eq!(qc.cno[&0], (30.0 * 39.0, 39));
+
+        let json = qc.to_json("UBX001.25O");
+        assert_eq!(json["epochs"], 39);
+        assert_eq!(json["cycle_slips"], 1);
+
+        let text = qc.to_text("UBX001.25O");
+        assert!(text.contains("DATA GAPS:         1"));
+    }
+}
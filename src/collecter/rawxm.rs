@@ -9,20 +9,34 @@ pub struct Rawxm {
     pub cp: f64,
     pub dop: f32,
     pub cno: u8,
+    /// Glonass frequency channel (+7)
+    pub freq_id: u8,
+    /// u-Blox signal identifier
+    pub sig_id: u8,
 }
 
 impl std::fmt::Display for Rawxm {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}({}) pr={:.7E} cp={:.7E} dop={:.7E} cno={}",
-            self.t, self.sv, self.pr, self.cp, self.dop, self.cno,
+            "{}({}:{}) pr={:.7E} cp={:.7E} dop={:.7E} cno={}",
+            self.t, self.sv, self.sig_id, self.pr, self.cp, self.dop, self.cno,
         )
     }
 }
 
 impl Rawxm {
-    pub fn new(t: Epoch, sv: SV, pr: f64, cp: f64, dop: f32, cno: u8) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        t: Epoch,
+        sv: SV,
+        pr: f64,
+        cp: f64,
+        dop: f32,
+        cno: u8,
+        freq_id: u8,
+        sig_id: u8,
+    ) -> Self {
         Self {
             t,
             sv,
@@ -30,6 +44,13 @@ impl Rawxm {
             cp,
             dop,
             cno,
+            freq_id,
+            sig_id,
         }
     }
+
+    /// Returns Glonass frequency channel number
+    pub fn glonass_channel(&self) -> i8 {
+        self.freq_id as i8 - 7
+    }
 }
@@ -5,8 +5,12 @@ use hifitime::{
     prelude::{Duration, Epoch, Formatter, TimeScale},
 };
 
+use crate::collecter::{phase_shift::PhaseShiftMode, qc::QcFormat};
+
 use rinex::{
-    prelude::{Constellation, Observable},
+    hardware::Antenna,
+    marker::GeodeticMarker,
+    prelude::{Constellation, Header, Observable},
     production::{FFU, PPU},
 };
 
@@ -21,13 +25,63 @@ pub struct Settings {
     pub short_filename: bool,
     pub prefix: Option<String>,
     pub agency: Option<String>,
+    pub observer: Option<String>,
     pub operator: Option<String>,
+    /// [GeodeticMarker], when described
+    pub marker: Option<GeodeticMarker>,
+    /// Receiver [Antenna], when described
+    pub antenna: Option<Antenna>,
+    /// Sampling period, epochs are decimated to this interval
+    pub sampling_period: Duration,
+    /// Number of epochs we gather, before releasing the header
+    pub learning_epochs: usize,
+    /// Observables are learned during the learning period
+    pub learn_observables: bool,
+    /// Receiver clock offset is removed from the observations
+    pub clock_offset_applied: bool,
+    /// Receiver clock millisecond jumps are removed from the observations
+    pub remove_clock_jumps: bool,
+    /// Receiver clock bias refers to the time pulse (PPS) output:
+    /// TIM-TP quantization error is applied to the RINEX Clock
+    pub clk_pps: bool,
+    /// Elevation mask [deg], lower observations are removed
+    pub elev_mask_deg: Option<f64>,
+    /// Quality control reports, produced for each file
+    pub qc_formats: Vec<QcFormat>,
+    /// Phase shift handling, when desired
+    pub phase_shift: Option<PhaseShiftMode>,
     /// Timescale to be used in Observations
     pub timescale: TimeScale,
+    /// Observations are expressed in GLONASST, which is UTC(SU):
+    /// epochs are UTC, but the time system is declared as GLO.
+    pub glonass_time: bool,
     pub observables: HashMap<Constellation, Vec<Observable>>,
 }
 
 impl Settings {
+    /// Applies the station description to this [Header].
+    /// This is common to all output products.
+    pub fn customize_header(&self, header: &mut Header) {
+        if let Some(observer) = &self.observer {
+            header.observer = Some(observer.clone());
+        }
+
+        if let Some(operator) = &self.operator {
+            header.run_by = Some(operator.clone());
+        }
+
+        if let Some(agency) = &self.agency {
+            header.agency = Some(agency.clone());
+        }
+
+        header.geodetic_marker = self.marker.clone();
+    }
+
+    /// Start of the file period that contains this [Epoch]
+    pub fn period_start(&self, t: Epoch) -> Epoch {
+        t.floor(self.period)
+    }
+
     pub fn filename(&self, is_nav: bool, t: Epoch) -> String {
         let mut filepath = if let Some(prefix) = &self.prefix {
             format!("{}/", prefix)
@@ -53,7 +107,67 @@ impl Settings {
         filepath
     }
 
+    /// Raw UBX archive file name
+    pub fn ubx_filename(&self, t: Epoch) -> String {
+        let mut filepath = self.aux_filename(t, "ubx");
+
+        if self.gzip {
+            filepath.push_str(".gz");
+        }
+
+        filepath
+    }
+
+    /// RINEX Clock file name
+    pub fn clk_filename(&self, t: Epoch) -> String {
+        let ppu: PPU = self.period.into();
+        let ffu: FFU = self.sampling_period.into();
+
+        let mut filepath = if let Some(prefix) = &self.prefix {
+            format!("{}/", prefix)
+        } else {
+            "".to_string()
+        };
+
+        let fmt = Format::from_str("%Y%j%H%M").unwrap();
+        let formatter = Formatter::new(self.period_start(t), fmt);
+
+        filepath.push_str(&format!(
+            "{}{}_R_{}_{}_{}_CLK.CLK",
+            self.name, self.country, formatter, ppu, ffu
+        ));
+
+        if self.gzip {
+            filepath.push_str(".gz");
+        }
+
+        filepath
+    }
+
+    /// File name of auxiliary (non RINEX) products,
+    /// that follow the same naming convention and rotation.
+    pub fn aux_filename(&self, t: Epoch, extension: &str) -> String {
+        let ppu: PPU = self.period.into();
+
+        let mut filepath = if let Some(prefix) = &self.prefix {
+            format!("{}/", prefix)
+        } else {
+            "".to_string()
+        };
+
+        let fmt = Format::from_str("%Y%j%H%M").unwrap();
+        let formatter = Formatter::new(self.period_start(t), fmt);
+
+        filepath.push_str(&format!(
+            "{}{}_R_{}_{}.{}",
+            self.name, self.country, formatter, ppu, extension
+        ));
+
+        filepath
+    }
+
     fn obs_v2_filename(&self, t: Epoch) -> String {
+        let t = self.period_start(t);
         let (y, _, _, _, _, _, _) = t.to_gregorian_utc();
 
         let fmt = Format::from_str("%j").unwrap();
@@ -62,6 +176,17 @@ impl Settings {
         let mut formatted = self.name.to_string();
 
         formatted.push_str(&formatter.to_string());
+
+        // sub-daily files: hourly session letter
+        if self.period < Duration::from_days(1.0) {
+            let fmt = Format::from_str("%H").unwrap();
+            let hour = Formatter::new(t, fmt)
+                .to_string()
+                .parse::<u8>()
+                .unwrap_or(0);
+            formatted.push((b'a' + hour) as char);
+        }
+
         formatted.push('.');
 
         formatted.push_str(&format!("{:02}", y - 2000));
@@ -81,15 +206,15 @@ impl Settings {
 
     fn obs_v3_filename(&self, t: Epoch) -> String {
         let ppu: PPU = self.period.into();
-        let ffu: FFU = Duration::from_seconds(30.0).into();
+        let ffu: FFU = self.sampling_period.into();
 
         let mut formatted = format!("{}{}_R_", self.name, self.country);
 
-        let fmt = Format::from_str("%Y%j").unwrap();
-        let formatter = Formatter::new(t, fmt);
+        let fmt = Format::from_str("%Y%j%H%M").unwrap();
+        let formatter = Formatter::new(self.period_start(t), fmt);
 
         formatted.push_str(&formatter.to_string());
-        formatted.push_str("0000_");
+        formatted.push('_');
 
         formatted.push_str(&ppu.to_string());
         formatted.push('_');
@@ -168,11 +293,24 @@ mod test {
         let mut settings = Settings {
             major: 3,
             agency: None,
+            observer: None,
             operator: None,
+            marker: None,
+            antenna: None,
             gzip: false,
             crinex: false,
             prefix: None,
+            sampling_period: Duration::from_seconds(30.0),
+            learning_epochs: 1,
+            learn_observables: false,
+            phase_shift: None,
+            clock_offset_applied: false,
+            remove_clock_jumps: false,
+            elev_mask_deg: None,
+            clk_pps: false,
+            qc_formats: Vec::new(),
             timescale: TimeScale::GPST,
+            glonass_time: false,
             short_filename: true,
             name: "UBX".to_string(),
             country: "FRA".to_string(),
@@ -189,6 +327,10 @@ mod test {
 
         settings.gzip = true;
         assert_eq!(settings.obs_v2_filename(t0), "UBX001.20D.gz");
+
+        settings.period = Duration::from_hours(1.0);
+        let t = Epoch::from_str("2020-01-01T13:25:00 GPST").unwrap();
+        assert_eq!(settings.obs_v2_filename(t), "UBX001n.20D.gz");
     }
 
     #[test]
@@ -196,12 +338,25 @@ mod test {
         let mut settings = Settings {
             major: 3,
             agency: None,
+            observer: None,
             operator: None,
+            marker: None,
+            antenna: None,
             gzip: false,
             crinex: false,
             prefix: None,
             short_filename: false,
+            sampling_period: Duration::from_seconds(30.0),
+            learning_epochs: 1,
+            learn_observables: false,
+            phase_shift: None,
+            clock_offset_applied: false,
+            remove_clock_jumps: false,
+            elev_mask_deg: None,
+            clk_pps: false,
+            qc_formats: Vec::new(),
             timescale: TimeScale::GPST,
+            glonass_time: false,
             name: "UBX".to_string(),
             country: "FRA".to_string(),
             period: Duration::from_days(1.0),
@@ -228,5 +383,17 @@ mod test {
             settings.obs_v3_filename(t0),
             "UBXFRA_R_20200010000_01D_30S_MO.crx.gz"
         );
+
+        settings.period = Duration::from_hours(1.0);
+        settings.sampling_period = Duration::from_seconds(1.0);
+
+        let t = Epoch::from_str("2020-01-01T13:25:00 GPST").unwrap();
+
+        assert_eq!(
+            settings.obs_v3_filename(t),
+            "UBXFRA_R_20200011300_01H_01S_MO.crx.gz"
+        );
+
+        assert_eq!(settings.ubx_filename(t), "UBXFRA_R_20200011300_01H.ubx.gz");
     }
 }
@@ -0,0 +1,201 @@
+use std::{io::Write, str::FromStr};
+
+use rinex::prelude::{Epoch, SV};
+
+use crate::collecter::{
+    dataset::{Collecter as DatasetCollecter, DatasetFormat, DatasetRecord},
+    Message,
+};
+
+/// Supported skyplot dataset formats
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SkyplotFormat {
+    /// Comma separated values, one line per SV
+    Csv,
+    /// JSON lines, one object per epoch
+    Json,
+}
+
+impl FromStr for SkyplotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown skyplot format \"{}\"", s)),
+        }
+    }
+}
+
+impl DatasetFormat for SkyplotFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "sat.csv",
+            Self::Json => "sat.json",
+        }
+    }
+
+    fn header(&self) -> &'static str {
+        match self {
+            Self::Csv => "epoch,sv,elev_deg,azim_deg,cno_dbhz,pr_res_m,used,health,eph_available\n",
+            Self::Json => "",
+        }
+    }
+}
+
+/// SV health, as reported by NAV-SAT
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum SvHealth {
+    #[default]
+    Unknown,
+    Healthy,
+    Unhealthy,
+}
+
+impl std::fmt::Display for SvHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Unknown => write!(f, "unknown"),
+            Self::Healthy => write!(f, "healthy"),
+            Self::Unhealthy => write!(f, "unhealthy"),
+        }
+    }
+}
+
+/// Satellite state, as reported by NAV-SAT
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SatelliteState {
+    pub sv: SV,
+    /// Elevation [deg]
+    pub elev_deg: i8,
+    /// Azimuth [deg]
+    pub azim_deg: i16,
+    /// Carrier to noise ratio [dB.Hz]
+    pub cno_dbhz: u8,
+    /// Pseudo range residual [m]
+    pub pr_res_m: f64,
+    /// SV is used in the navigation solution
+    pub used: bool,
+    /// [SvHealth]
+    pub health: SvHealth,
+    /// Ephemeris is available
+    pub eph_available: bool,
+}
+
+/// Satellite states of one epoch
+#[derive(Debug, Clone, Default)]
+pub struct SkyEpoch {
+    /// [Epoch] of this report
+    pub epoch: Epoch,
+    pub satellites: Vec<SatelliteState>,
+}
+
+impl DatasetRecord for SkyEpoch {
+    type Format = SkyplotFormat;
+
+    const NAME: &'static str = "skyplot dataset";
+
+    fn from_message(msg: Message) -> Option<Self> {
+        match msg {
+            Message::Satellites(sky) => Some(sky),
+            _ => None,
+        }
+    }
+
+    fn epoch(&self) -> Epoch {
+        self.epoch
+    }
+
+    /// Formats this [SkyEpoch]
+    fn format<W: Write>(&self, format: SkyplotFormat, w: &mut W) -> std::io::Result<()> {
+        match format {
+            SkyplotFormat::Csv => {
+                for sat in self.satellites.iter() {
+                    writeln!(
+                        w,
+                        "{},{},{},{},{},{:.1},{},{},{}",
+                        self.epoch,
+                        sat.sv,
+                        sat.elev_deg,
+                        sat.azim_deg,
+                        sat.cno_dbhz,
+                        sat.pr_res_m,
+                        sat.used,
+                        sat.health,
+                        sat.eph_available,
+                    )?;
+                }
+                Ok(())
+            },
+            SkyplotFormat::Json => writeln!(w, "{}", self.json()),
+        }
+    }
+}
+
+impl SkyEpoch {
+    /// Formats this [SkyEpoch] as JSON object
+    fn json(&self) -> serde_json::Value {
+        let satellites = self
+            .satellites
+            .iter()
+            .map(|sat| {
+                serde_json::json!({
+                    "sv": sat.sv.to_string(),
+                    "elev_deg": sat.elev_deg,
+                    "azim_deg": sat.azim_deg,
+                    "cno_dbhz": sat.cno_dbhz,
+                    "pr_res_m": sat.pr_res_m,
+                    "used": sat.used,
+                    "health": sat.health.to_string(),
+                    "eph_available": sat.eph_available,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        serde_json::json!({
+            "epoch": self.epoch.to_string(),
+            "satellites": satellites,
+        })
+    }
+}
+
+/// Skyplot dataset [Collecter]
+pub type Collecter = DatasetCollecter<SkyEpoch>;
+
+#[cfg(test)]
+mod test {
+    use super::{SatelliteState, SkyEpoch, SkyplotFormat, SvHealth};
+    use crate::collecter::dataset::DatasetRecord;
+    use hifitime::prelude::Epoch;
+    use rinex::prelude::SV;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_skyplot_formats() {
+        let sky = SkyEpoch {
+            epoch: Epoch::from_str("2025-02-25T12:00:00 GPST").unwrap(),
+            satellites: vec![SatelliteState {
+                sv: SV::from_str("G05").unwrap(),
+                elev_deg: 45,
+                azim_deg: 270,
+                cno_dbhz: 42,
+                pr_res_m: -1.2,
+                used: true,
+                health: SvHealth::Healthy,
+                eph_available: true,
+            }],
+        };
+
+        let mut csv = Vec::new();
+        sky.format(SkyplotFormat::Csv, &mut csv).unwrap();
+
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.ends_with(",G05,45,270,42,-1.2,true,healthy,true\n"));
+
+        let json = sky.json();
+        assert_eq!(json["satellites"][0]["sv"], "G05");
+        assert_eq!(json["satellites"][0]["elev_deg"], 45);
+        assert_eq!(json["satellites"][0]["health"], "healthy");
+    }
+}
@@ -1,52 +1,431 @@
 use ublox::{
-    AlignmentToReferenceTime, CfgMsgAllPorts, CfgMsgAllPortsBuilder, CfgPrtUart, CfgPrtUartBuilder,
-    CfgRate, CfgRateBuilder, DataBits, InProtoMask, MgaGloEph, MgaGpsEph, MonVer, NavClock, NavEoe,
-    NavPvt, NavSat, OutProtoMask, PacketRef, Parity, Parser, RxmRawx, StopBits, UartMode,
-    UartPortId, UbxPacketMeta, UbxPacketRequest,
+    AlignmentToReferenceTime, CfgLayerSet, CfgMsgAllPorts, CfgMsgAllPortsBuilder, CfgNav5,
+    CfgPrtUart, CfgPrtUartBuilder, CfgRate, CfgRateBuilder, DataBits, InProtoMask, MgaGloEph,
+    MgaGpsEph, MonHw, MonVer, NavClock, NavEoe, NavPvt, NavSat, NavTimeUTC, OutProtoMask,
+    PacketRef, Parity, Parser, RxmRawx, SecUniqId, StopBits, TimTm2, TimTp, UartMode, UartPortId,
+    UbxPacketMeta, UbxPacketRequest,
 };
 
 use std::io::Write;
 
 use serialport::SerialPort;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::utils::from_timescale;
+use crate::{
+    collecter::Message,
+    survey::SurveyedPosition,
+    ubx::{
+        cfg_cfg_frame, cfg_device_mask, cfg_msg_frame, cfg_navx5_min_cno_frame, cfg_rst_frame,
+        cfg_tmode2_frame, cfg_valget_frame, cfg_valset_frame, diff_cfg, diff_legacy_gnss,
+        format_cfg_value, is_legacy_hardware, is_legacy_protocol, parse_cfg_items,
+        parse_protocol_version, tmode_ecef, ubx_frame, CFG_CFG, CFG_CFG_ALL, CFG_GNSS, CFG_ITFM,
+        CFG_KEY_ALL, CFG_MSG, CFG_NAV5, CFG_NAVX5, CFG_NAVX5_MIN_CNO_OFFSET, CFG_RATE_MEAS,
+        CFG_RATE_NAV, CFG_RATE_TIMEREF, CFG_TMODE2, CFG_TMODE_ECEF, CFG_TMODE_ECEF_HP,
+        CFG_TMODE_FIXED_POS_ACC, CFG_TMODE_MODE, CFG_TMODE_POS_TYPE, CFG_TMODE_SVIN_ACC_LIMIT,
+        CFG_TMODE_SVIN_MIN_DUR, CFG_VALGET, CFG_VALGET_MAX_ITEMS, CFG_VALSET, MON_RF, NAV_SVIN,
+        NAV_TIMEGPS, NAV_TIMELS, SEC_SIG,
+    },
+};
+
+use log::{debug, error, info, warn};
 
-use log::{debug, error};
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::{collecter::Message, UbloxSettings};
+use crate::UbloxSettings;
 
-use tokio::sync::mpsc::Sender;
+/// Maximal duration we wait for an acknowledgment or a poll response
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Error returned when the receiver did not respond to this message in time
+fn response_timeout(class: u8, id: u8) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::TimedOut,
+        format!(
+            "0x{:02x} 0x{:02x}: no response within {}s",
+            class,
+            id,
+            RESPONSE_TIMEOUT.as_secs()
+        ),
+    )
+}
 
 pub struct Device {
     pub port: Box<dyn SerialPort>,
     pub parser: Parser<Vec<u8>>,
+    /// Raw stream is forwarded to the archive, when desired.
+    /// The channel is unbounded, so no byte is ever dropped.
+    pub archive: Option<UnboundedSender<Message>>,
 }
 
 impl Device {
-    pub fn configure(&mut self, settings: &UbloxSettings, buf: &mut [u8], tx: Sender<Message>) {
-        let mut vec = Vec::with_capacity(1024);
-
-        self.read_version(buf, tx).unwrap();
+    pub fn configure(&mut self, settings: &mut UbloxSettings, buf: &mut [u8]) {
+        self.read_version(buf, settings).unwrap();
+        self.read_serial_number(buf, settings).unwrap();
 
         if settings.rx_clock {
             self.enable_nav_clock(buf);
         }
 
         self.enable_nav_eoe(buf);
+        self.enable_nav_timeutc(buf);
+        self.enable_nav_timegps(buf);
+        self.enable_nav_timels(buf);
         self.enable_nav_pvt(buf);
         self.enable_nav_sat(buf);
         self.enable_obs_rinex(buf);
 
-        let time_ref = from_timescale(settings.timescale);
+        if settings.timemark {
+            self.enable_tim_tm2(buf);
+        }
+
+        if settings.timepulse {
+            self.enable_tim_tp(buf);
+        }
+
+        if settings.anti_spoofing {
+            self.enable_monitoring(buf, settings.legacy_cfg);
+        }
+
+        let measure_rate_ms = (settings.measurement_period.total_nanoseconds() / 1_000_000) as u16;
+        self.apply_cfg_rate(
+            buf,
+            measure_rate_ms,
+            settings.solutions_ratio,
+            settings.time_ref,
+        );
+
+        if settings.legacy_cfg {
+            self.apply_legacy_cfg(buf, settings);
+        } else {
+            self.apply_valset_cfg(buf, settings, measure_rate_ms);
+        }
 
-        let measure_rate_ms = (settings.sampling_period.total_nanoseconds() / 1_000_000) as u16;
-        self.apply_cfg_rate(buf, measure_rate_ms, settings.solutions_ratio, time_ref);
+        if !settings.save_layers.is_empty() {
+            self.save_cfg(buf, settings.save_layers);
+        }
+    }
+
+    /// Applies constellations and signals configuration with CFG-VALSET
+    fn apply_valset_cfg(&mut self, buf: &mut [u8], settings: &UbloxSettings, measure_rate_ms: u16) {
+        let mut vec = Vec::with_capacity(1024);
 
-        settings.to_ram_volatile_cfg(&mut vec);
+        settings.to_valset_cfg(&mut vec);
 
         self.write_all(&vec)
-            .unwrap_or_else(|e| panic!("Failed to apply RAM config: {}", e));
+            .unwrap_or_else(|e| panic!("Failed to apply config: {}", e));
+
+        self.wait_for_ack_raw(buf, CFG_VALSET.0, CFG_VALSET.1)
+            .unwrap_or_else(|e| panic!("UBX-CFG-VALSET NACK: {}", e));
+
+        // header (6) + version, layers, reserved (4) .. checksum (2)
+        let mut requested = parse_cfg_items(&vec[10..vec.len() - 2]);
+
+        let mut items = settings.nav_cfg_items();
+        items.extend(settings.monitor_cfg_items());
+
+        if !items.is_empty() {
+            self.write_all(&cfg_valset_frame(settings.layers(), &items))
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAVSPG/ITFM error: {}", e));
+
+            self.wait_for_ack_raw(buf, CFG_VALSET.0, CFG_VALSET.1)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAVSPG/ITFM NACK: {}", e));
+
+            debug!("UBX-CFG-NAVSPG/ITFM applied");
+            requested.extend(items);
+        }
+
+        requested.push((CFG_RATE_MEAS, measure_rate_ms.to_le_bytes().to_vec()));
+        requested.push((
+            CFG_RATE_NAV,
+            settings.solutions_ratio.to_le_bytes().to_vec(),
+        ));
+        requested.push((CFG_RATE_TIMEREF, vec![settings.time_ref as u8]));
+
+        self.verify_cfg(buf, &requested, settings.strict_cfg);
+    }
+
+    /// Saves the current configuration to these non volatile layers (BBR, Flash)
+    pub fn save_cfg(&mut self, buffer: &mut [u8], layers: CfgLayerSet) {
+        self.write_all(&cfg_cfg_frame(0, CFG_CFG_ALL, 0, cfg_device_mask(layers)))
+            .unwrap_or_else(|e| panic!("UBX-CFG-CFG error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_CFG.0, CFG_CFG.1)
+            .unwrap_or_else(|e| panic!("UBX-CFG-CFG NACK: {}", e));
+
+        info!("receiver configuration saved ({:?})", layers);
+    }
+
+    /// Restores the default configuration (clears the non volatile layers)
+    /// and resets the receiver.
+    pub fn reset_cfg(&mut self, buffer: &mut [u8]) {
+        let device_mask = cfg_device_mask(CfgLayerSet::BBR | CfgLayerSet::FLASH);
+
+        self.write_all(&cfg_cfg_frame(CFG_CFG_ALL, 0, CFG_CFG_ALL, device_mask))
+            .unwrap_or_else(|e| panic!("UBX-CFG-CFG error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_CFG.0, CFG_CFG.1)
+            .unwrap_or_else(|e| panic!("UBX-CFG-CFG NACK: {}", e));
+
+        // CFG-RST is not acknowledged
+        self.write_all(&cfg_rst_frame())
+            .unwrap_or_else(|e| panic!("UBX-CFG-RST error: {}", e));
+
+        info!("receiver configuration restored to defaults, receiver reset");
+    }
+
+    /// Reads back these configuration items (RAM layer), with CFG-VALGET
+    pub fn read_cfg(
+        &mut self,
+        buffer: &mut [u8],
+        keys: &[u32],
+    ) -> std::io::Result<Vec<(u32, Vec<u8>)>> {
+        let mut items = Vec::with_capacity(keys.len());
+
+        for keys in keys.chunks(CFG_VALGET_MAX_ITEMS) {
+            let payload = self.poll_raw(
+                buffer,
+                &cfg_valget_frame(0, keys),
+                CFG_VALGET.0,
+                CFG_VALGET.1,
+            )?;
+
+            items.extend(parse_cfg_items(payload.get(4..).unwrap_or_default()));
+        }
+
+        Ok(items)
+    }
+
+    /// Reads back the configuration and compares it to the requested configuration.
+    /// Mismatches are reported, or are fatal in strict mode.
+    pub fn verify_cfg(&mut self, buffer: &mut [u8], requested: &[(u32, Vec<u8>)], strict: bool) {
+        let keys = requested.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+
+        let actual = self
+            .read_cfg(buffer, &keys)
+            .unwrap_or_else(|e| panic!("UBX-CFG-VALGET error: {}", e));
+
+        let mismatches = diff_cfg(requested, &actual);
+
+        for (key, requested, actual) in mismatches.iter() {
+            error!(
+                "configuration mismatch: 0x{:08x} requested={} actual={}",
+                key,
+                format_cfg_value(requested),
+                actual
+                    .as_ref()
+                    .map(|value| format_cfg_value(value))
+                    .unwrap_or_else(|| "not reported".to_string()),
+            );
+        }
+
+        if mismatches.is_empty() {
+            info!(
+                "receiver configuration verified ({} items)",
+                requested.len()
+            );
+        } else if strict {
+            panic!(
+                "receiver configuration mismatch ({} items). Aborting.",
+                mismatches.len()
+            );
+        }
+    }
+
+    /// Prints the current receiver configuration (RAM layer)
+    pub fn dump_config(&mut self, buffer: &mut [u8], legacy: bool) {
+        if legacy {
+            for (name, (class, id)) in [("CFG-GNSS", CFG_GNSS), ("CFG-NAVX5", CFG_NAVX5)] {
+                let payload = self
+                    .poll_raw(buffer, &ubx_frame(class, id, &[]), class, id)
+                    .unwrap_or_else(|e| panic!("UBX-{} error: {}", name, e));
+
+                let payload = payload
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                println!("{}: {}", name, payload);
+            }
+
+            let (_, _, nav5) = self
+                .read_nav5(buffer)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAV5 error: {}", e));
+
+            println!("CFG-NAV5: {}", nav5);
+            return;
+        }
+
+        let mut position = 0u16;
+
+        loop {
+            let payload = self
+                .poll_raw(
+                    buffer,
+                    &cfg_valget_frame(position, &[CFG_KEY_ALL]),
+                    CFG_VALGET.0,
+                    CFG_VALGET.1,
+                )
+                .unwrap_or_else(|e| panic!("UBX-CFG-VALGET error: {}", e));
+
+            let items = parse_cfg_items(payload.get(4..).unwrap_or_default());
+
+            for (key, value) in items.iter() {
+                println!("0x{:08x} = {}", key, format_cfg_value(value));
+            }
+
+            if items.len() < CFG_VALGET_MAX_ITEMS {
+                break;
+            }
+
+            position += items.len() as u16;
+        }
+    }
+
+    /// Applies constellations and signals configuration with the legacy
+    /// CFG-GNSS message, for receivers that do not support CFG-VALSET (M8 series).
+    fn apply_legacy_cfg(&mut self, buffer: &mut [u8], settings: &UbloxSettings) {
+        if settings.l5 {
+            warn!("L5 is not supported by legacy receivers");
+        }
+
+        let requested = settings.to_legacy_cfg_gnss();
+
+        self.write_all(&requested)
+            .unwrap_or_else(|e| panic!("UBX-CFG-GNSS error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_GNSS.0, CFG_GNSS.1)
+            .unwrap_or_else(|e| panic!("UBX-CFG-GNSS NACK: {}", e));
+
+        debug!("UBX-CFG-GNSS applied");
+
+        // read back
+        let actual = self
+            .poll_raw(
+                buffer,
+                &ubx_frame(CFG_GNSS.0, CFG_GNSS.1, &[]),
+                CFG_GNSS.0,
+                CFG_GNSS.1,
+            )
+            .unwrap_or_else(|e| panic!("UBX-CFG-GNSS error: {}", e));
+
+        let mismatches = diff_legacy_gnss(&requested[6..requested.len() - 2], &actual);
+
+        for gnss_id in mismatches.iter() {
+            error!("configuration mismatch: CFG-GNSS block #{}", gnss_id);
+        }
+
+        let mut mismatches = mismatches.len();
+
+        if let Some(itfm) = settings.to_legacy_cfg_itfm() {
+            self.write_all(&itfm)
+                .unwrap_or_else(|e| panic!("UBX-CFG-ITFM error: {}", e));
+
+            self.wait_for_ack_raw(buffer, CFG_ITFM.0, CFG_ITFM.1)
+                .unwrap_or_else(|e| panic!("UBX-CFG-ITFM NACK: {}", e));
+
+            debug!("UBX-CFG-ITFM applied");
+        }
+
+        if let Some(nav5) = settings.to_legacy_cfg_nav5() {
+            self.write_all(&nav5)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAV5 error: {}", e));
+
+            self.wait_for_ack_raw(buffer, CFG_NAV5.0, CFG_NAV5.1)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAV5 NACK: {}", e));
+
+            debug!("UBX-CFG-NAV5 applied");
+
+            // read back
+            let (dyn_model, min_elev, _) = self
+                .read_nav5(buffer)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAV5 error: {}", e));
+
+            if let Some(requested) = settings.dyn_model {
+                if requested.value() != dyn_model {
+                    error!(
+                        "configuration mismatch: dynamic model requested={:?} actual={}",
+                        requested, dyn_model
+                    );
+                    mismatches += 1;
+                }
+            }
+
+            if let Some(requested) = settings.min_elev_deg {
+                if requested != min_elev {
+                    error!(
+                        "configuration mismatch: elevation mask requested={}° actual={}°",
+                        requested, min_elev
+                    );
+                    mismatches += 1;
+                }
+            }
+        }
+
+        if let Some(min_cno) = settings.min_cno_dbhz {
+            let navx5_poll = ubx_frame(CFG_NAVX5.0, CFG_NAVX5.1, &[]);
+
+            let current = self
+                .poll_raw(buffer, &navx5_poll, CFG_NAVX5.0, CFG_NAVX5.1)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAVX5 error: {}", e));
+
+            self.write_all(&cfg_navx5_min_cno_frame(&current, min_cno))
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAVX5 error: {}", e));
+
+            self.wait_for_ack_raw(buffer, CFG_NAVX5.0, CFG_NAVX5.1)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAVX5 NACK: {}", e));
+
+            debug!("UBX-CFG-NAVX5 applied");
+
+            // read back
+            let actual = self
+                .poll_raw(buffer, &navx5_poll, CFG_NAVX5.0, CFG_NAVX5.1)
+                .unwrap_or_else(|e| panic!("UBX-CFG-NAVX5 error: {}", e));
+
+            let actual = actual.get(CFG_NAVX5_MIN_CNO_OFFSET).copied();
+
+            if actual != Some(min_cno) {
+                error!(
+                    "configuration mismatch: C/N0 mask requested={}dB.Hz actual={:?}",
+                    min_cno, actual
+                );
+                mismatches += 1;
+            }
+        }
+
+        if mismatches == 0 {
+            info!("receiver configuration verified (legacy)");
+        } else if settings.strict_cfg {
+            panic!("receiver configuration mismatch (legacy). Aborting.");
+        }
+    }
+
+    /// Reads the legacy navigation settings (CFG-NAV5): dynamic model,
+    /// elevation mask [°] and complete description.
+    fn read_nav5(&mut self, buffer: &mut [u8]) -> std::io::Result<(u8, i8, String)> {
+        self.write_all(&UbxPacketRequest::request_for::<CfgNav5>().into_packet_bytes())?;
+
+        let mut nav5 = None;
+
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+
+        while nav5.is_none() {
+            if Instant::now() > deadline {
+                return Err(response_timeout(CFG_NAV5.0, CFG_NAV5.1));
+            }
+
+            self.consume_cb(buffer, |packet| {
+                if let PacketRef::CfgNav5(pkt) = packet {
+                    nav5 = Some((
+                        pkt.dyn_model() as u8,
+                        pkt.min_elev_degrees(),
+                        format!("{:?}", pkt),
+                    ));
+                }
+            })?;
+        }
+
+        Ok(nav5.unwrap_or_default())
     }
 
     pub fn open(port_str: &str, baud: u32, buffer: &mut [u8]) -> Self {
@@ -57,7 +436,11 @@ impl Device {
             .unwrap_or_else(|e| panic!("Failed to open {} port: {}", port_str, e));
 
         let parser = Parser::default();
-        let mut dev = Self { port, parser };
+        let mut dev = Self {
+            port,
+            parser,
+            archive: None,
+        };
 
         for portid in [UartPortId::Uart1, UartPortId::Uart2] {
             // Enable UBX protocol on selected UART port
@@ -104,47 +487,265 @@ impl Device {
         buffer: &mut [u8],
         mut cb: T,
     ) -> std::io::Result<()> {
-        loop {
-            let nbytes = self.read_port(buffer)?;
-            if nbytes == 0 {
-                break;
+        while self.consume_cb(buffer, &mut cb)? > 0 {}
+        Ok(())
+    }
+
+    /// Reads and parses one chunk of data, returns the number of bytes
+    /// that were read (0 on port timeout).
+    fn consume_cb<T: FnMut(PacketRef)>(
+        &mut self,
+        buffer: &mut [u8],
+        mut cb: T,
+    ) -> std::io::Result<usize> {
+        let nbytes = self.read_port(buffer)?;
+        if nbytes == 0 {
+            return Ok(0);
+        }
+
+        if let Some(archive) = &self.archive {
+            if let Err(e) = archive.send(Message::Raw(buffer[..nbytes].to_vec())) {
+                error!("missed raw UBX bytes: {}", e);
             }
+        }
 
-            // parser.consume adds the buffer to its internal buffer, and
-            // returns an iterator-like object we can use to process the packets
-            let mut it = self.parser.consume_ubx(&buffer[..nbytes]);
-            loop {
-                match it.next() {
-                    Some(Ok(packet)) => {
-                        cb(packet);
-                    },
-                    Some(Err(e)) => {
-                        error!("parsing error: {}", e);
-                    },
-                    None => {
-                        // We've eaten all the packets we have
-                        break;
-                    },
-                }
+        // parser.consume adds the buffer to its internal buffer, and
+        // returns an iterator-like object we can use to process the packets
+        let mut it = self.parser.consume_ubx(&buffer[..nbytes]);
+        loop {
+            match it.next() {
+                Some(Ok(packet)) => {
+                    cb(packet);
+                },
+                Some(Err(e)) => {
+                    error!("parsing error: {}", e);
+                },
+                None => {
+                    // We've eaten all the packets we have
+                    break;
+                },
             }
         }
-        Ok(())
+
+        Ok(nbytes)
     }
 
     pub fn wait_for_ack<T: UbxPacketMeta>(&mut self, buffer: &mut [u8]) -> std::io::Result<()> {
+        self.wait_for_ack_raw(buffer, T::CLASS, T::ID)
+    }
+
+    /// Waits for the acknowledgment of a message that we encoded ourselves
+    pub fn wait_for_ack_raw(
+        &mut self,
+        buffer: &mut [u8],
+        class: u8,
+        id: u8,
+    ) -> std::io::Result<()> {
         let mut found_packet = false;
+        let mut nacked = false;
+
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+
         while !found_packet {
-            self.consume_all_cb(buffer, |packet| {
-                if let PacketRef::AckAck(ack) = packet {
-                    if ack.class() == T::CLASS && ack.msg_id() == T::ID {
+            if Instant::now() > deadline {
+                return Err(response_timeout(class, id));
+            }
+
+            self.consume_cb(buffer, |packet| match packet {
+                PacketRef::AckAck(ack) => {
+                    if ack.class() == class && ack.msg_id() == id {
                         found_packet = true;
                     }
-                }
+                },
+                PacketRef::AckNak(nak) => {
+                    if nak.class() == class && nak.msg_id() == id {
+                        nacked = true;
+                    }
+                },
+                _ => {},
             })?;
+
+            if nacked {
+                return Err(std::io::Error::other(format!(
+                    "0x{:02x} 0x{:02x} rejected",
+                    class, id
+                )));
+            }
         }
         Ok(())
     }
 
+    /// Sends a poll request that we encoded ourselves,
+    /// and returns the payload of the response.
+    pub fn poll_raw(
+        &mut self,
+        buffer: &mut [u8],
+        request: &[u8],
+        class: u8,
+        id: u8,
+    ) -> std::io::Result<Vec<u8>> {
+        self.write_all(request)?;
+
+        let mut response = Option::<Vec<u8>>::None;
+        let mut nacked = false;
+
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+
+        while response.is_none() {
+            if Instant::now() > deadline {
+                return Err(response_timeout(class, id));
+            }
+
+            self.consume_cb(buffer, |packet| match packet {
+                PacketRef::Unknown(pkt) => {
+                    if pkt.class == class && pkt.msg_id == id {
+                        response = Some(pkt.payload.to_vec());
+                    }
+                },
+                PacketRef::AckNak(nak) => {
+                    if nak.class() == class && nak.msg_id() == id {
+                        nacked = true;
+                    }
+                },
+                _ => {},
+            })?;
+
+            if nacked {
+                return Err(std::io::Error::other(format!(
+                    "0x{:02x} 0x{:02x} poll rejected",
+                    class, id
+                )));
+            }
+        }
+
+        Ok(response.unwrap_or_default())
+    }
+
+    pub fn enable_tim_tp(&mut self, buffer: &mut [u8]) {
+        self.write_all(
+            &CfgMsgAllPortsBuilder::set_rate_for::<TimTp>([1, 1, 1, 1, 1, 1]).into_packet_bytes(),
+        )
+        .unwrap_or_else(|e| panic!("UBX-TIM-TP error: {}", e));
+
+        self.wait_for_ack::<CfgMsgAllPorts>(buffer)
+            .unwrap_or_else(|e| panic!("UBX-TIM-TP error: {}", e));
+
+        debug!("UBX-TIM-TP enabled");
+    }
+
+    /// Starts the receiver survey-in (TMODE3, or TMODE2 on legacy receivers),
+    /// that completes after this minimal duration and once accuracy is reached.
+    pub fn start_survey_in(
+        &mut self,
+        buffer: &mut [u8],
+        min_duration_s: u32,
+        accuracy_m: f64,
+        legacy_cfg: bool,
+    ) {
+        if legacy_cfg {
+            let acc_limit = (accuracy_m * 1.0E3).round() as u32;
+
+            self.apply_tmode2(
+                buffer,
+                &cfg_tmode2_frame(1, (0, 0, 0), 0, min_duration_s, acc_limit),
+            );
+
+            self.enable_nav_svin(buffer);
+            debug!("UBX-TMODE2 survey-in started");
+            return;
+        }
+
+        let acc_limit = (accuracy_m * 1.0E4).round() as u32;
+
+        let frame = cfg_valset_frame(
+            CfgLayerSet::RAM,
+            &[
+                (CFG_TMODE_MODE, vec![1]),
+                (
+                    CFG_TMODE_SVIN_MIN_DUR,
+                    min_duration_s.to_le_bytes().to_vec(),
+                ),
+                (CFG_TMODE_SVIN_ACC_LIMIT, acc_limit.to_le_bytes().to_vec()),
+            ],
+        );
+
+        self.write_all(&frame)
+            .unwrap_or_else(|e| panic!("UBX-CFG-TMODE error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_VALSET.0, CFG_VALSET.1)
+            .unwrap_or_else(|e| panic!("UBX-CFG-TMODE error: {}", e));
+
+        self.enable_nav_svin(buffer);
+        debug!("UBX-TMODE3 survey-in started");
+    }
+
+    /// Enables UBX-NAV-SVIN, that reports the survey-in progress
+    fn enable_nav_svin(&mut self, buffer: &mut [u8]) {
+        self.write_all(&cfg_msg_frame(NAV_SVIN.0, NAV_SVIN.1, 1))
+            .unwrap_or_else(|e| panic!("UBX-NAV-SVIN error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_MSG.0, CFG_MSG.1)
+            .unwrap_or_else(|e| panic!("UBX-NAV-SVIN error: {}", e));
+    }
+
+    /// Applies this UBX-CFG-TMODE2 frame. Only timing receivers support it.
+    fn apply_tmode2(&mut self, buffer: &mut [u8], frame: &[u8]) {
+        self.write_all(frame)
+            .unwrap_or_else(|e| panic!("UBX-CFG-TMODE2 error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_TMODE2.0, CFG_TMODE2.1)
+            .unwrap_or_else(|e| {
+                panic!(
+                    "UBX-CFG-TMODE2 error: {}. Timing modes require a timing receiver (like NEO-M8T)",
+                    e
+                )
+            });
+    }
+
+    /// Pushes fixed position timing mode (TMODE3, or TMODE2 on legacy receivers)
+    /// to the receiver
+    pub fn apply_fixed_position(
+        &mut self,
+        buffer: &mut [u8],
+        position: &SurveyedPosition,
+        legacy_cfg: bool,
+    ) {
+        let (x, y, z) = position.ecef_m;
+
+        if legacy_cfg {
+            let cm = |coord_m: f64| (coord_m * 1.0E2).round() as i32;
+            let acc = (position.accuracy_m * 1.0E3).round() as u32;
+
+            self.apply_tmode2(
+                buffer,
+                &cfg_tmode2_frame(2, (cm(x), cm(y), cm(z)), acc, 0, 0),
+            );
+
+            debug!("UBX-TMODE2 fixed position applied");
+            return;
+        }
+
+        let mut items = vec![(CFG_TMODE_MODE, vec![2]), (CFG_TMODE_POS_TYPE, vec![0])];
+
+        for (i, coord_m) in [x, y, z].iter().enumerate() {
+            let (cm, hp) = tmode_ecef(*coord_m);
+
+            items.push((CFG_TMODE_ECEF[i], cm.to_le_bytes().to_vec()));
+            items.push((CFG_TMODE_ECEF_HP[i], hp.to_le_bytes().to_vec()));
+        }
+
+        let acc = (position.accuracy_m * 1.0E4).round() as u32;
+        items.push((CFG_TMODE_FIXED_POS_ACC, acc.to_le_bytes().to_vec()));
+
+        self.write_all(&cfg_valset_frame(CfgLayerSet::RAM, &items))
+            .unwrap_or_else(|e| panic!("UBX-CFG-TMODE error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_VALSET.0, CFG_VALSET.1)
+            .unwrap_or_else(|e| panic!("UBX-CFG-TMODE error: {}", e));
+
+        debug!("UBX-TMODE3 fixed position applied");
+    }
+
     pub fn request_mga_gps_eph(&mut self) {
         match self.write_all(&UbxPacketRequest::request_for::<MgaGpsEph>().into_packet_bytes()) {
             Ok(_) => {
@@ -167,7 +768,11 @@ impl Device {
         }
     }
 
-    pub fn read_version(&mut self, buffer: &mut [u8], tx: Sender<Message>) -> std::io::Result<()> {
+    pub fn read_version(
+        &mut self,
+        buffer: &mut [u8],
+        settings: &mut UbloxSettings,
+    ) -> std::io::Result<()> {
         self.write_all(&UbxPacketRequest::request_for::<MonVer>().into_packet_bytes())
             .unwrap_or_else(|e| panic!("Failed to request firmware version: {}", e));
 
@@ -176,15 +781,68 @@ impl Device {
         while !packet_found {
             self.consume_all_cb(buffer, |packet| {
                 if let PacketRef::MonVer(pkt) = packet {
-                    let firmware = pkt.hardware_version();
                     debug!("U-Blox Software version: {}", pkt.software_version());
-                    debug!("U-Blox Firmware version: {}", firmware);
+                    debug!("U-Blox Hardware version: {}", pkt.hardware_version());
 
-                    tx.try_send(Message::FirmwareVersion(pkt.hardware_version().to_string()))
-                        .unwrap_or_else(|e| {
-                            panic!("internal error reading firmware version: {}", e)
-                        });
+                    let mut firmware = pkt.software_version().trim().to_string();
+                    let mut protocol = None;
 
+                    for extension in pkt.extension() {
+                        debug!("U-Blox extension: {}", extension);
+
+                        if let Some(fw) = extension.strip_prefix("FWVER=") {
+                            firmware = fw.trim().to_string();
+                        } else if let Some(version) = parse_protocol_version(extension) {
+                            protocol = Some(version);
+                        } else if let Some(model) = extension.strip_prefix("MOD=") {
+                            // user definition prevails
+                            if settings.model.is_none() {
+                                settings.model = Some(model.trim().to_string());
+                            }
+                        }
+                    }
+
+                    settings.legacy_cfg = match protocol {
+                        Some(version) => is_legacy_protocol(version),
+                        // M8 series (or older) hardware
+                        None => is_legacy_hardware(pkt.hardware_version()),
+                    };
+
+                    if settings.legacy_cfg {
+                        info!("legacy receiver: using CFG-GNSS configuration");
+                    }
+
+                    settings.firmware = Some(firmware);
+                    packet_found = true;
+                }
+            })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_serial_number(
+        &mut self,
+        buffer: &mut [u8],
+        settings: &mut UbloxSettings,
+    ) -> std::io::Result<()> {
+        self.write_all(&UbxPacketRequest::request_for::<SecUniqId>().into_packet_bytes())
+            .unwrap_or_else(|e| panic!("Failed to request serial number: {}", e));
+
+        let mut packet_found = false;
+
+        while !packet_found {
+            self.consume_all_cb(buffer, |packet| {
+                if let PacketRef::SecUniqId(pkt) = packet {
+                    let sn = pkt
+                        .unique_id()
+                        .iter()
+                        .map(|b| format!("{:02X}", b))
+                        .collect::<String>();
+
+                    debug!("U-Blox serial number: {}", sn);
+
+                    settings.sn = Some(sn);
                     packet_found = true;
                 }
             })?;
@@ -243,6 +901,67 @@ impl Device {
         debug!("UBX-NAV-EOE enabled");
     }
 
+    fn enable_nav_timeutc(&mut self, buffer: &mut [u8]) {
+        self.write_all(
+            &CfgMsgAllPortsBuilder::set_rate_for::<NavTimeUTC>([1, 1, 1, 1, 1, 1])
+                .into_packet_bytes(),
+        )
+        .unwrap_or_else(|e| panic!("UBX-NAV-TIMEUTC error: {}", e));
+
+        self.wait_for_ack::<CfgMsgAllPorts>(buffer)
+            .unwrap_or_else(|e| panic!("UBX-NAV-TIMEUTC error: {}", e));
+
+        debug!("UBX-NAV-TIMEUTC enabled");
+    }
+
+    /// Enables the interference monitoring messages: MON-HW,
+    /// and MON-RF and SEC-SIG on modern receivers.
+    fn enable_monitoring(&mut self, buffer: &mut [u8], legacy: bool) {
+        self.write_all(
+            &CfgMsgAllPortsBuilder::set_rate_for::<MonHw>([1, 1, 1, 1, 1, 1]).into_packet_bytes(),
+        )
+        .unwrap_or_else(|e| panic!("UBX-MON-HW error: {}", e));
+
+        self.wait_for_ack::<CfgMsgAllPorts>(buffer)
+            .unwrap_or_else(|e| panic!("UBX-MON-HW error: {}", e));
+
+        debug!("UBX-MON-HW enabled");
+
+        if legacy {
+            return;
+        }
+
+        for (name, (class, id)) in [("MON-RF", MON_RF), ("SEC-SIG", SEC_SIG)] {
+            self.write_all(&cfg_msg_frame(class, id, 1))
+                .unwrap_or_else(|e| panic!("UBX-{} error: {}", name, e));
+
+            self.wait_for_ack_raw(buffer, CFG_MSG.0, CFG_MSG.1)
+                .unwrap_or_else(|e| panic!("UBX-{} error: {}", name, e));
+
+            debug!("UBX-{} enabled", name);
+        }
+    }
+
+    fn enable_nav_timegps(&mut self, buffer: &mut [u8]) {
+        self.write_all(&cfg_msg_frame(NAV_TIMEGPS.0, NAV_TIMEGPS.1, 1))
+            .unwrap_or_else(|e| panic!("UBX-NAV-TIMEGPS error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_MSG.0, CFG_MSG.1)
+            .unwrap_or_else(|e| panic!("UBX-NAV-TIMEGPS error: {}", e));
+
+        debug!("UBX-NAV-TIMEGPS enabled");
+    }
+
+    fn enable_nav_timels(&mut self, buffer: &mut [u8]) {
+        self.write_all(&cfg_msg_frame(NAV_TIMELS.0, NAV_TIMELS.1, 1))
+            .unwrap_or_else(|e| panic!("UBX-NAV-TIMELS error: {}", e));
+
+        self.wait_for_ack_raw(buffer, CFG_MSG.0, CFG_MSG.1)
+            .unwrap_or_else(|e| panic!("UBX-NAV-TIMELS error: {}", e));
+
+        debug!("UBX-NAV-TIMELS enabled");
+    }
+
     fn enable_nav_clock(&mut self, buffer: &mut [u8]) {
         self.write_all(
             &CfgMsgAllPortsBuilder::set_rate_for::<NavClock>([1, 1, 1, 1, 1, 1])
@@ -284,6 +1003,18 @@ impl Device {
         debug!("UBX-NAV-PVT enabled");
     }
 
+    pub fn enable_tim_tm2(&mut self, buffer: &mut [u8]) {
+        self.write_all(
+            &CfgMsgAllPortsBuilder::set_rate_for::<TimTm2>([1, 1, 1, 1, 1, 1]).into_packet_bytes(),
+        )
+        .unwrap_or_else(|e| panic!("UBX-TIM-TM2 error: {}", e));
+
+        self.wait_for_ack::<CfgMsgAllPorts>(buffer)
+            .unwrap_or_else(|e| panic!("UBX-TIM-TM2 error: {}", e));
+
+        debug!("UBX-TIM-TM2 enabled");
+    }
+
     // pub fn read_gnss(&mut self, buffer: &mut [u8]) -> std::io::Result<()> {
     //     self.write_all(&UbxPacketRequest::request_for::<MonGnss>().into_packet_bytes())
     //         .unwrap_or_else(|e| panic!("Failed to request firmware version: {}", e));
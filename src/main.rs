@@ -29,23 +29,45 @@ use tokio::{
 
 use rinex::prelude::{Constellation, Duration, Epoch, Observable, TimeScale, SV};
 
-use ublox::{NavStatusFlags, NavStatusFlags2, NavTimeUtcFlags, PacketRef, RecStatFlags};
+use ublox::{
+    NavPvtFlags, NavSatSvHealth, NavStatusFlags, NavStatusFlags2, NavTimeUtcFlags, PacketRef,
+    RecStatFlags, TimTm2TimeBase,
+};
 
 mod cli;
 mod collecter;
 mod device;
+mod monitor;
+mod station;
+mod survey;
+mod time;
 mod ubx;
 mod utils;
 
 use crate::{
     cli::Cli,
     collecter::{
-        ephemeris::EphemerisBuilder, navigation::Collecter as NavCollecter,
-        observation::Collecter as ObsCollecter, rawxm::Rawxm, Message,
+        archive::Collecter as ArchiveCollecter,
+        broadcast,
+        clock::{ClockState, Collecter as ClkCollecter},
+        ephemeris::EphemerisBuilder,
+        event::{Event, EventLog},
+        navigation::Collecter as NavCollecter,
+        observation::Collecter as ObsCollecter,
+        pvt::{CarrierSolution, Collecter as PvtCollecter, Pvt},
+        rawxm::Rawxm,
+        skyplot::{Collecter as SkyplotCollecter, SatelliteState, SkyEpoch, SvHealth},
+        Message,
     },
     device::Device,
-    ubx::Settings as UbloxSettings,
-    utils::to_constellation,
+    monitor::{Monitor, MonitorLog, RfStatus, SignalSecurity},
+    survey::{Survey, SurveyIn, SurveyedPosition},
+    time::{GpsTime, LeapSecondInfo, TimeKeeper},
+    ubx::{
+        nav_clock_state, Settings as UbloxSettings, MON_RF, NAV_SVIN, NAV_TIMEGPS, NAV_TIMELS,
+        SEC_SIG,
+    },
+    utils::{from_week_tow, geodetic_to_ecef, to_constellation},
 };
 
 #[tokio::main]
@@ -65,6 +87,23 @@ pub async fn main() {
     // RINEX settings
     let settings = cli.rinex_settings();
 
+    // Observation RINEX products
+    let products = cli.rinex_products();
+
+    // Time marks log
+    let mut event_log = cli.event_log().map(|path| EventLog::new(path));
+
+    // Jamming and spoofing monitoring
+    let mut monitor = if cli.anti_spoofing() {
+        let log = cli.monitor_log().map(|path| MonitorLog::new(path));
+        Some(Monitor::new(cli.jamming_threshold(), log))
+    } else {
+        None
+    };
+
+    // Jamming and spoofing alerts, to be declared
+    let mut alerts = Vec::<(Epoch, String)>::new();
+
     // init
     let mut buffer = [0; 8192];
     let mut uptime = Duration::default();
@@ -80,71 +119,200 @@ pub async fn main() {
 
     let timescale = ubx_settings.timescale;
 
-    // Time
+    // Time: host clock (logs only), until receiver time is valid
     let mut t_utc = Epoch::now()
         .unwrap_or_else(|e| panic!("Failed to determine system time: {}", e))
         .to_time_scale(TimeScale::UTC);
 
-    let mut nav_utc_week = t_utc.to_time_of_week().0;
+    // Receiver time
+    let mut timekeeper = TimeKeeper::default();
 
-    let mut t_gpst = t_utc.to_time_scale(TimeScale::GPST);
+    let mut end_of_nav_epoch = false;
+
+    // Tokio
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
-    let mut nav_gpst = t_gpst;
-    let mut nav_gpst_week = t_gpst.to_time_of_week().0;
+    // Open device
+    let mut device = Device::open(port, baud_rate, &mut buffer);
 
-    let mut t_gst = t_utc.to_time_scale(TimeScale::GST);
+    if cli.reset_config() {
+        device.reset_cfg(&mut buffer);
+        return;
+    }
 
-    let mut nav_gst = t_gst;
-    let mut nav_gst_week = t_gst.to_time_of_week().0;
+    if cli.dump_config() {
+        device
+            .read_version(&mut buffer, &mut ubx_settings)
+            .unwrap_or_else(|e| panic!("Failed to read receiver version: {}", e));
 
-    let mut t_bdt = t_utc.to_time_scale(TimeScale::BDT);
+        device.dump_config(&mut buffer, ubx_settings.legacy_cfg);
+        return;
+    }
 
-    let mut nav_bdt = t_bdt;
-    let mut nav_bdt_week = t_bdt.to_time_of_week().0;
+    device.configure(&mut ubx_settings, &mut buffer);
 
-    let mut end_of_nav_epoch = false;
+    // Receiver position
+    let position_file = cli.position_file().cloned();
+    let known_position = position_file
+        .as_ref()
+        .and_then(|path| SurveyedPosition::from_file(path));
 
-    // Tokio
-    let (shutdown_tx, shutdown_rx) = watch::channel(true);
+    let mut survey = Option::<Survey>::None;
+    let mut survey_in = false;
+    let mut surveyed = Option::<SurveyedPosition>::None;
 
-    // Observation RINEX
-    let (obs_tx, obs_rx) = mpsc::channel(32);
-    let mut obs_collecter = ObsCollecter::new(
-        settings.clone(),
-        ubx_settings.clone(),
-        shutdown_rx.clone(),
-        obs_rx,
-    );
+    match known_position {
+        Some(position) => {
+            if cli.fixed_mode() {
+                device.apply_fixed_position(&mut buffer, &position, ubx_settings.legacy_cfg);
+            }
+        },
+        None => {
+            if let Some(accuracy) = cli.survey_accuracy() {
+                if cli.survey_in() {
+                    let min_duration_s = cli.survey_time().to_seconds() as u32;
+                    device.start_survey_in(
+                        &mut buffer,
+                        min_duration_s,
+                        accuracy,
+                        ubx_settings.legacy_cfg,
+                    );
+                    survey_in = true;
+                } else {
+                    survey = Some(Survey::new(accuracy, 10));
+                }
+                info!("surveying position, until {:.3}m accuracy", accuracy);
+            }
+        },
+    }
+
+    // Observation RINEX: one collecter per product
+    let mut obs_txs = Vec::with_capacity(products.len());
+    let mut obs_handles = Vec::with_capacity(products.len());
 
     // Navigation RINEX
     let (nav_tx, nav_rx) = mpsc::channel(32);
     let mut nav_collecter = NavCollecter::new(
-        t_utc,
         settings.clone(),
         ubx_settings.clone(),
         shutdown_rx.clone(),
         nav_rx,
     );
 
-    // Open device
-    let mut device = Device::open(port, baud_rate, &mut buffer);
-
-    device.configure(&ubx_settings, &mut buffer, obs_tx.clone());
-
     if ubx_settings.rawxm {
-        tokio::spawn(async move {
-            debug!("{} - Observation mode deployed", t_utc);
-            obs_collecter.run().await;
-        });
+        for product in products.iter() {
+            let (obs_tx, obs_rx) = mpsc::channel(32);
+
+            let mut obs_collecter = ObsCollecter::new(
+                product.clone(),
+                ubx_settings.clone(),
+                shutdown_rx.clone(),
+                obs_rx,
+            );
+
+            obs_txs.push(obs_tx);
+
+            obs_handles.push(tokio::spawn(async move {
+                debug!("{} - Observation mode deployed", t_utc);
+                obs_collecter.run().await;
+            }));
+        }
     }
 
-    if ubx_settings.ephemeris {
-        tokio::spawn(async move {
+    let nav_handle = if ubx_settings.ephemeris {
+        Some(tokio::spawn(async move {
             debug!("{} - Navigation  mode deployed", t_utc);
             nav_collecter.run().await;
+        }))
+    } else {
+        None
+    };
+
+    // Clock RINEX
+    let (clk_tx, clk_handle) = if cli.clk() {
+        let (clk_tx, clk_rx) = mpsc::channel(32);
+        let mut clk_collecter = ClkCollecter::new(settings.clone(), ubx_settings.clone(), clk_rx);
+
+        let handle = tokio::spawn(async move {
+            debug!("{} - Clock mode deployed", t_utc);
+            clk_collecter.run().await;
         });
+
+        (Some(clk_tx), Some(handle))
+    } else {
+        (None, None)
+    };
+
+    // collecters interested in the receiver position
+    let position_txs = obs_txs
+        .iter()
+        .chain(clk_tx.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if let Some(position) = known_position {
+        let _ = broadcast(&position_txs, Message::FixedPosition(position.ecef_m));
     }
 
+    // PVT log
+    let pvt_formats = cli.pvt_formats();
+
+    let (pvt_tx, pvt_handle) = if !pvt_formats.is_empty() {
+        let (pvt_tx, pvt_rx) = mpsc::channel(32);
+        let mut pvt_collecter = PvtCollecter::new(settings.clone(), pvt_formats, pvt_rx);
+
+        let handle = tokio::spawn(async move {
+            debug!("{} - PVT log deployed", t_utc);
+            pvt_collecter.run().await;
+        });
+
+        (Some(pvt_tx), Some(handle))
+    } else {
+        (None, None)
+    };
+
+    // Skyplot dataset
+    let skyplot_formats = cli.skyplot_formats();
+
+    let (skyplot_tx, skyplot_handle) = if !skyplot_formats.is_empty() {
+        let (skyplot_tx, skyplot_rx) = mpsc::channel(32);
+        let mut skyplot_collecter =
+            SkyplotCollecter::new(settings.clone(), skyplot_formats, skyplot_rx);
+
+        let handle = tokio::spawn(async move {
+            debug!("{} - skyplot dataset deployed", t_utc);
+            skyplot_collecter.run().await;
+        });
+
+        (Some(skyplot_tx), Some(handle))
+    } else {
+        (None, None)
+    };
+
+    // collecters interested in the satellite states
+    let satellites_txs = obs_txs
+        .iter()
+        .chain(skyplot_tx.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    // Raw UBX archive
+    let (archive_tx, archive_handle) = if cli.ubx_archive() {
+        let (archive_tx, archive_rx) = mpsc::unbounded_channel();
+        let mut archive_collecter = ArchiveCollecter::new(settings.clone(), archive_rx);
+
+        device.archive = Some(archive_tx.clone());
+
+        let handle = tokio::spawn(async move {
+            debug!("{} - UBX archiving deployed", t_utc);
+            archive_collecter.run().await;
+        });
+
+        (Some(archive_tx), Some(handle))
+    } else {
+        (None, None)
+    };
+
     tokio::spawn(async move {
         signal::ctrl_c()
             .await
@@ -159,18 +327,50 @@ pub async fn main() {
         let _ = device.consume_all_cb(&mut buffer, |packet| {
             match packet {
                 PacketRef::CfgNav5(pkt) => {
-                    // Dynamic model
-                    let _dyn_model = pkt.dyn_model();
+                    debug!(
+                        "dynamic model: {:?}, elevation mask: {}°",
+                        pkt.dyn_model(),
+                        pkt.min_elev_degrees()
+                    );
                 },
                 PacketRef::RxmRawx(pkt) => {
+                    let stat = pkt.rec_stat();
+
+                    if stat.intersects(RecStatFlags::LEAP_SEC) {
+                        timekeeper.set_leap_seconds(pkt.leap_s());
+                    }
+
+                    if !timekeeper.is_valid() {
+                        trace!("receiver time is not valid yet: skipping measurements");
+                        return;
+                    }
+
                     let gpst_tow_nanos = (pkt.rcv_tow() * 1.0E9).round() as u64;
-                    t_gpst = Epoch::from_time_of_week(pkt.week() as u32, gpst_tow_nanos, timescale);
 
-                    let stat = pkt.rec_stat();
+                    timekeeper.update_week(pkt.week() as u32, (gpst_tow_nanos / 1_000_000) as u32);
+
+                    // RAWX is always expressed in GPST
+                    let t_gpst = Epoch::from_time_of_week(
+                        pkt.week() as u32,
+                        gpst_tow_nanos,
+                        TimeScale::GPST,
+                    );
+
+                    let t = timekeeper.to_timescale(t_gpst, settings.timescale);
+
+                    if let Some(archive_tx) = &archive_tx {
+                        if let Err(e) = archive_tx.send(Message::Timestamp(t)) {
+                            error!("{} - missed archive timestamp: {}", t_gpst, e);
+                        }
+                    }
 
                     if stat.intersects(RecStatFlags::CLK_RESET) {
                         error!("{} - clock reset!", t_gpst);
                         warn!("{} - declaring phase cycle slip!", t_gpst);
+
+                        if let Err(e) = broadcast(&obs_txs, Message::ClockReset(t)) {
+                            error!("{} missed clock reset: {}", t_gpst, e);
+                        }
                     }
 
                     for meas in pkt.measurements() {
@@ -183,7 +383,8 @@ pub async fn main() {
                         let dop = meas.do_mes();
                         let _dop_stddev = meas.do_stdev();
 
-                        // let freq_id = meas.freq_id();
+                        let freq_id = meas.freq_id();
+                        let sig_id = meas.sig_id();
                         let gnss_id = meas.gnss_id();
                         let cno = meas.cno();
 
@@ -198,15 +399,9 @@ pub async fn main() {
                         let prn = meas.sv_id();
                         let sv = SV::new(constell, prn);
 
-                        let t = if settings.timescale == TimeScale::GPST {
-                            t_gpst
-                        } else {
-                            t_gpst.to_time_scale(settings.timescale)
-                        };
-
-                        let rawxm = Rawxm::new(t, sv, pr, cp, dop, cno);
+                        let rawxm = Rawxm::new(t, sv, pr, cp, dop, cno, freq_id, sig_id);
 
-                        match obs_tx.try_send(Message::Measurement(rawxm)) {
+                        match broadcast(&obs_txs, Message::Measurement(rawxm)) {
                             Ok(_) => {
                                 debug!("{}", rawxm);
                             },
@@ -216,8 +411,95 @@ pub async fn main() {
                         }
                     }
                 },
-                PacketRef::MonHw(pkt) => {},
+                PacketRef::TimTm2(pkt) => {
+                    let flags = pkt.flags();
+
+                    let timescale = match flags.time_base() {
+                        TimTm2TimeBase::Utc => TimeScale::UTC,
+                        _ => TimeScale::GPST,
+                    };
+
+                    let edges = [
+                        (
+                            "rising",
+                            flags.new_rising_edge(),
+                            pkt.wn_r(),
+                            pkt.tow_ms_r(),
+                            pkt.tow_sub_ms_r(),
+                        ),
+                        (
+                            "falling",
+                            flags.new_falling_edge(),
+                            pkt.wn_f(),
+                            pkt.tow_ms_f(),
+                            pkt.tow_sub_ms_f(),
+                        ),
+                    ];
+
+                    for (edge, new, week, tow_ms, tow_sub_ms_ns) in edges {
+                        if !new {
+                            continue;
+                        }
+
+                        if !flags.time_valid() {
+                            warn!("EXTINT{} - {} edge: time is not valid", pkt.ch(), edge);
+                            continue;
+                        }
+
+                        let tow_nanos = tow_ms as u64 * 1_000_000 + tow_sub_ms_ns as u64;
+
+                        let t = from_week_tow(week as u32, tow_nanos, timescale)
+                            .to_time_scale(settings.timescale);
+
+                        debug!("{} - EXTINT{} {} edge", t, pkt.ch(), edge);
+
+                        if let Some(event_log) = event_log.as_mut() {
+                            event_log.log(t, pkt.ch(), edge, pkt.count(), pkt.acc_est());
+                        }
+
+                        let mut event = Event::external(t);
+
+                        event.comments.push(format!(
+                            "EXTINT{} {} EDGE",
+                            pkt.ch(),
+                            edge.to_uppercase()
+                        ));
+
+                        if let Err(e) = broadcast(&obs_txs, Message::Event(event)) {
+                            error!("{} - missed time mark: {}", t, e);
+                        }
+                    }
+                },
+                PacketRef::MonHw(pkt) => {
+                    let status = RfStatus::from_mon_hw(
+                        pkt.flags(),
+                        pkt.jam_ind(),
+                        pkt.agc_cnt(),
+                        pkt.noise_per_ms(),
+                    );
+
+                    if let (Some(monitor), Some(t)) = (monitor.as_mut(), timekeeper.gpst()) {
+                        let t = timekeeper.to_timescale(t, settings.timescale);
+
+                        if let Some(alert) = monitor.update_rf(t, "MON-HW", &status) {
+                            alerts.push((t, alert));
+                        }
+                    }
+                },
                 PacketRef::NavSat(pkt) => {
+                    let t = match timekeeper.gpst_from_itow(pkt.itow()) {
+                        Some(t) => timekeeper.to_timescale(t, settings.timescale),
+                        None => {
+                            trace!("receiver time is not valid yet: skipping nav-sat");
+                            return;
+                        },
+                    };
+
+                    let mut sky = SkyEpoch {
+                        epoch: t,
+                        satellites: Vec::new(),
+                    };
+
                     for sv in pkt.svs() {
                         let constellation = to_constellation(sv.gnss_id());
 
@@ -227,27 +509,36 @@ pub async fn main() {
 
                         let constellation = constellation.unwrap();
 
-                        let _elev = sv.elev();
-                        let _azim = sv.azim();
-                        let _pr_res = sv.pr_res();
-                        let _flags = sv.flags();
+                        let flags = sv.flags();
 
-                        let _sv = SV {
-                            constellation,
-                            prn: sv.sv_id(),
+                        let health = match flags.health() {
+                            NavSatSvHealth::Healthy => SvHealth::Healthy,
+                            NavSatSvHealth::Unhealthy => SvHealth::Unhealthy,
+                            _ => SvHealth::Unknown,
                         };
 
-                        // flags.sv_used()
-                        //flags.health();
-                        //flags.quality_ind();
-                        //flags.differential_correction_available();
-                        //flags.ephemeris_available();
+                        sky.satellites.push(SatelliteState {
+                            sv: SV::new(constellation, sv.sv_id()),
+                            elev_deg: sv.elev(),
+                            azim_deg: sv.azim(),
+                            cno_dbhz: sv.cno(),
+                            pr_res_m: sv.pr_res() as f64,
+                            used: flags.sv_used(),
+                            health,
+                            eph_available: flags.ephemeris_available(),
+                        });
+                    }
+
+                    if let Err(e) = broadcast(&satellites_txs, Message::Satellites(sky)) {
+                        error!("{} - missed satellite states: {}", t, e);
                     }
                 },
                 PacketRef::NavTimeUTC(pkt) => {
-                    if pkt.valid().intersects(NavTimeUtcFlags::VALID_UTC) {
+                    let valid = pkt.valid();
+
+                    let t = if valid.intersects(NavTimeUtcFlags::VALID_UTC) {
                         // leap seconds already known
-                        let e = Epoch::maybe_from_gregorian(
+                        Epoch::maybe_from_gregorian(
                             pkt.year().into(),
                             pkt.month(),
                             pkt.day(),
@@ -256,8 +547,21 @@ pub async fn main() {
                             pkt.sec(),
                             pkt.nanos() as u32,
                             TimeScale::UTC,
-                        );
+                        )
+                        .ok()
+                    } else {
+                        None
+                    };
+
+                    if let Some(t) = t {
+                        t_utc = t;
                     }
+
+                    timekeeper.update_utc(
+                        t,
+                        valid.intersects(NavTimeUtcFlags::VALID_TOW),
+                        valid.intersects(NavTimeUtcFlags::VALID_WKN),
+                    );
                 },
                 PacketRef::NavStatus(pkt) => {
                     pkt.itow();
@@ -272,13 +576,13 @@ pub async fn main() {
                     trace!("Uptime: {}", uptime);
                 },
                 PacketRef::NavEoe(pkt) => {
-                    let nav_gpst_itow_nanos = pkt.itow() as u64 * 1_000_000;
-
-                    nav_gpst = Epoch::from_time_of_week(
-                        nav_gpst_week,
-                        nav_gpst_itow_nanos,
-                        TimeScale::GPST,
-                    );
+                    let nav_gpst = match timekeeper.gpst_from_itow(pkt.itow()) {
+                        Some(t) => t,
+                        None => {
+                            trace!("receiver time is not valid yet: skipping end of epoch");
+                            return;
+                        },
+                    };
 
                     end_of_nav_epoch = true;
 
@@ -299,9 +603,75 @@ pub async fn main() {
                             pkt.longitude()
                         );
                     }
+
+                    let flags = pkt.flags();
+
+                    let ecef =
+                        geodetic_to_ecef(pkt.latitude(), pkt.longitude(), pkt.height_meters());
+
+                    if flags.intersects(NavPvtFlags::GPS_FIX_OK) {
+                        if let Err(e) = broadcast(&position_txs, Message::Position(ecef)) {
+                            error!("{} - missed position update: {}", t_utc, e);
+                        }
+
+                        if let Some(survey) = survey.as_mut() {
+                            if let Some(position) = survey.add(ecef) {
+                                surveyed = Some(position);
+                            } else {
+                                trace!("survey: {:.3}m accuracy", survey.accuracy());
+                            }
+                        }
+                    }
+
+                    if let Some(pvt_tx) = &pvt_tx {
+                        // valid date and time
+                        if pkt.valid() & 0x03 == 0x03 {
+                            let epoch =
+                                Epoch::from_gregorian(y, m, d, hh, mm, ss, 0, TimeScale::UTC)
+                                    + Duration::from_nanoseconds(pkt.nanosecond() as f64);
+
+                            let carrier = if flags.intersects(NavPvtFlags::CARR_SOLN_FIXED) {
+                                CarrierSolution::Fixed
+                            } else if flags.intersects(NavPvtFlags::CARR_SOLN_FLOAT) {
+                                CarrierSolution::Float
+                            } else {
+                                CarrierSolution::None
+                            };
+
+                            let pvt = Pvt {
+                                epoch,
+                                carrier,
+                                ecef,
+                                fix_type: pkt.fix_type() as u8,
+                                fix_ok: flags.intersects(NavPvtFlags::GPS_FIX_OK),
+                                differential: flags.intersects(NavPvtFlags::DIFF_SOLN),
+                                num_sv: pkt.num_satellites(),
+                                latitude: pkt.latitude(),
+                                longitude: pkt.longitude(),
+                                height: pkt.height_meters(),
+                                height_msl: pkt.height_msl(),
+                                h_acc: pkt.horiz_accuracy(),
+                                v_acc: pkt.vert_accuracy(),
+                                pdop: pkt.pdop(),
+                            };
+
+                            if let Err(e) = pvt_tx.try_send(Message::Pvt(pvt)) {
+                                error!("{} - missed PVT solution: {}", epoch, e);
+                            }
+                        }
+                    }
                 },
                 PacketRef::MgaGpsEph(pkt) => {
                     debug!("{:?}", pkt);
+
+                    let t_gpst = match timekeeper.gpst() {
+                        Some(t) => t,
+                        None => {
+                            warn!("receiver time is not valid yet: dropping GPS ephemeris");
+                            return;
+                        },
+                    };
+
                     let sv = SV::new(Constellation::GPS, pkt.sv_id());
                     let eph = EphemerisBuilder::from_gps(pkt);
 
@@ -314,6 +684,15 @@ pub async fn main() {
                 },
                 PacketRef::MgaGloEph(pkt) => {
                     debug!("{:?}", pkt);
+
+                    let t_utc = match timekeeper.gpst() {
+                        Some(t) => timekeeper.to_timescale(t, TimeScale::UTC),
+                        None => {
+                            warn!("receiver time is not valid yet: dropping Glonass ephemeris");
+                            return;
+                        },
+                    };
+
                     let sv = SV::new(Constellation::GPS, pkt.sv_id());
                     let eph = EphemerisBuilder::from_glonass(pkt);
 
@@ -333,15 +712,41 @@ pub async fn main() {
                     // let _iono = IonMessage::KlobucharModel(kbmodel);
                 },
                 PacketRef::NavClock(pkt) => {
-                    let clock = pkt.clk_bias();
-                    match obs_tx.try_send(Message::Clock(clock)) {
+                    let (bias, drift) = nav_clock_state(&pkt);
+
+                    match broadcast(&obs_txs, Message::Clock((bias, drift))) {
                         Ok(_) => {
-                            debug!("{}", clock);
+                            debug!("clock bias={:.9E}s drift={:.9E}s/s", bias, drift);
                         },
                         Err(e) => {
                             error!("missed clock state: {}", e);
                         },
                     }
+
+                    let t_gpst = timekeeper.gpst_from_itow(pkt.itow());
+
+                    if let (Some(clk_tx), Some(t_gpst)) = (&clk_tx, t_gpst) {
+                        let state = ClockState {
+                            epoch: timekeeper.to_timescale(t_gpst, settings.timescale),
+                            bias,
+                            drift,
+                            bias_sigma: pkt.t_acc() as f64 * 1.0E-9,
+                            drift_sigma: pkt.f_acc() as f64 * 1.0E-12,
+                        };
+
+                        if let Err(e) = clk_tx.try_send(Message::ClockState(state)) {
+                            error!("{} - missed clock state: {}", state.epoch, e);
+                        }
+                    }
+                },
+                PacketRef::TimTp(pkt) => {
+                    if let Some(clk_tx) = &clk_tx {
+                        let qerr = pkt.q_err() as f64 * 1.0E-12;
+
+                        if let Err(e) = clk_tx.try_send(Message::QuantizationError(qerr)) {
+                            error!("missed quantization error: {}", e);
+                        }
+                    }
                 },
                 PacketRef::InfTest(pkt) => {
                     if let Some(msg) = pkt.message() {
@@ -368,10 +773,92 @@ pub async fn main() {
                         warn!("{}", msg);
                     }
                 },
+                PacketRef::Unknown(pkt) => {
+                    if let (Some(monitor), Some(t)) = (monitor.as_mut(), timekeeper.gpst()) {
+                        let t = timekeeper.to_timescale(t, settings.timescale);
+
+                        if (pkt.class, pkt.msg_id) == MON_RF {
+                            for status in RfStatus::decode_mon_rf(pkt.payload) {
+                                if let Some(alert) = monitor.update_rf(t, "MON-RF", &status) {
+                                    alerts.push((t, alert));
+                                }
+                            }
+                        }
+
+                        if (pkt.class, pkt.msg_id) == SEC_SIG {
+                            if let Some(security) = SignalSecurity::decode(pkt.payload) {
+                                for alert in monitor.update_security(t, &security) {
+                                    alerts.push((t, alert));
+                                }
+                            }
+                        }
+                    }
+
+                    if (pkt.class, pkt.msg_id) == NAV_TIMEGPS {
+                        if let Some(gps_time) = GpsTime::decode(pkt.payload) {
+                            timekeeper.update_gps_time(&gps_time);
+                        }
+                    }
+
+                    if (pkt.class, pkt.msg_id) == NAV_TIMELS {
+                        if let (Some(info), Some(t_gpst)) =
+                            (LeapSecondInfo::decode(pkt.payload), timekeeper.gpst())
+                        {
+                            timekeeper.update_leap(t_gpst, &info);
+                        }
+                    }
+
+                    if survey_in && (pkt.class, pkt.msg_id) == NAV_SVIN {
+                        if let Some(status) = SurveyIn::decode(pkt.payload) {
+                            trace!(
+                                "survey-in: {}s, {} observations, {:.3}m accuracy",
+                                status.duration_s,
+                                status.observations,
+                                status.position.accuracy_m
+                            );
+
+                            if status.valid && !status.active {
+                                survey_in = false;
+                                surveyed = Some(status.position);
+                            }
+                        }
+                    }
+                },
                 _ => {},
             }
         });
 
+        for (t, alert) in alerts.drain(..) {
+            warn!("{} - {}", t, alert);
+
+            let event = Event::comments(t, &[alert.as_str()]);
+
+            if let Err(e) = broadcast(&obs_txs, Message::Event(event)) {
+                error!("{} - missed monitoring event: {}", t, e);
+            }
+        }
+
+        if let Some(position) = surveyed.take() {
+            let (x, y, z) = position.ecef_m;
+
+            info!(
+                "surveyed position: x={:.3}m y={:.3}m z={:.3}m (accuracy {:.3}m)",
+                x, y, z, position.accuracy_m
+            );
+
+            if let Err(e) = broadcast(&position_txs, Message::FixedPosition(position.ecef_m)) {
+                error!("missed surveyed position: {}", e);
+            }
+
+            if let Some(path) = &position_file {
+                position.to_file(path);
+            }
+
+            if cli.fixed_mode() {
+                device.apply_fixed_position(&mut buffer, &position, ubx_settings.legacy_cfg);
+            }
+        }
+
         if end_of_nav_epoch {
             if ubx_settings.constellations.contains(&Constellation::GPS) {
                 device.request_mga_gps_eph();
@@ -386,5 +873,45 @@ pub async fn main() {
 
             end_of_nav_epoch = false;
         }
+
+        if *shutdown_rx.borrow() {
+            break;
+        }
     } // loop
+
+    info!("{} - shutting down", t_utc);
+
+    for obs_tx in obs_txs.iter() {
+        let _ = obs_tx.send(Message::Shutdown).await;
+    }
+
+    for handle in obs_handles {
+        let _ = handle.await;
+    }
+
+    if let (Some(clk_tx), Some(handle)) = (clk_tx, clk_handle) {
+        let _ = clk_tx.send(Message::Shutdown).await;
+        let _ = handle.await;
+    }
+
+    if let (Some(pvt_tx), Some(handle)) = (pvt_tx, pvt_handle) {
+        let _ = pvt_tx.send(Message::Shutdown).await;
+        let _ = handle.await;
+    }
+
+    if let (Some(skyplot_tx), Some(handle)) = (skyplot_tx, skyplot_handle) {
+        let _ = skyplot_tx.send(Message::Shutdown).await;
+        let _ = handle.await;
+    }
+
+    if let (Some(archive_tx), Some(handle)) = (archive_tx, archive_handle) {
+        device.archive = None;
+        let _ = archive_tx.send(Message::Shutdown);
+        let _ = handle.await;
+    }
+
+    if let Some(handle) = nav_handle {
+        let _ = nav_tx.send(Message::Shutdown).await;
+        let _ = handle.await;
+    }
 }
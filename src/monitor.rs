@@ -0,0 +1,367 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use rinex::prelude::Epoch;
+
+/// RF interference status, as reported by UBX-MON-HW or UBX-MON-RF
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct RfStatus {
+    /// RF block
+    pub block: u8,
+    /// Jamming state: 0 (unknown), 1 (ok), 2 (warning) or 3 (critical)
+    pub jamming_state: u8,
+    /// CW jamming indicator: 0 (no CW jamming) to 255 (strong CW jamming)
+    pub jam_ind: u8,
+    /// AGC monitor
+    pub agc_cnt: u16,
+    /// Noise level
+    pub noise_per_ms: u16,
+}
+
+impl RfStatus {
+    /// Builds [RfStatus] from UBX-MON-HW fields, that describe
+    /// the (single) RF block of legacy receivers.
+    pub fn from_mon_hw(flags: u8, jam_ind: u8, agc_cnt: u16, noise_per_ms: u16) -> Self {
+        Self {
+            block: 0,
+            jamming_state: (flags >> 2) & 0x03,
+            jam_ind,
+            agc_cnt,
+            noise_per_ms,
+        }
+    }
+
+    /// Decodes UBX-MON-RF payload, that describes each RF block
+    pub fn decode_mon_rf(payload: &[u8]) -> Vec<Self> {
+        let n_blocks = payload.get(1).copied().unwrap_or_default() as usize;
+
+        payload
+            .get(4..)
+            .unwrap_or_default()
+            .chunks_exact(24)
+            .take(n_blocks)
+            .map(|block| Self {
+                block: block[0],
+                jamming_state: block[1] & 0x03,
+                noise_per_ms: u16::from_le_bytes([block[12], block[13]]),
+                agc_cnt: u16::from_le_bytes([block[14], block[15]]),
+                jam_ind: block[16],
+            })
+            .collect()
+    }
+}
+
+/// Signal security status, as reported by UBX-SEC-SIG
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SignalSecurity {
+    /// Jamming state: 0 (unknown), 1 (ok), 2 (warning) or 3 (critical)
+    pub jamming_state: u8,
+    /// Spoofing state: 0 (unknown), 1 (no spoofing), 2 (spoofing indicated)
+    /// or 3 (multiple spoofing indications)
+    pub spoofing_state: u8,
+}
+
+impl SignalSecurity {
+    /// Decodes UBX-SEC-SIG payload (version 1 or 2)
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        match payload.first()? {
+            1 => {
+                let (jam_flags, spf_flags) = (*payload.get(4)?, *payload.get(8)?);
+
+                Some(Self {
+                    jamming_state: (jam_flags >> 1) & 0x03,
+                    spoofing_state: (spf_flags >> 1) & 0x07,
+                })
+            },
+            2 => {
+                let flags = *payload.get(4)?;
+
+                Some(Self {
+                    jamming_state: (flags >> 1) & 0x03,
+                    spoofing_state: (flags >> 4) & 0x07,
+                })
+            },
+            _ => None,
+        }
+    }
+}
+
+/// CSV time series of the interference monitoring
+pub struct MonitorLog {
+    w: BufWriter<File>,
+}
+
+impl MonitorLog {
+    /// Creates a new [MonitorLog]
+    pub fn new(path: &str) -> Self {
+        let fd =
+            File::create(path).unwrap_or_else(|e| panic!("Failed to open \"{}\": {}", path, e));
+
+        let mut w = BufWriter::new(fd);
+
+        writeln!(
+            w,
+            "epoch,source,block,jamming_state,jam_ind,agc_cnt,noise_per_ms,spoofing_state"
+        )
+        .unwrap_or_else(|e| panic!("Failed to write \"{}\": {}", path, e));
+
+        Self { w }
+    }
+
+    fn log_rf(&mut self, t: Epoch, source: &str, status: &RfStatus) {
+        let _ = writeln!(
+            self.w,
+            "{},{},{},{},{},{},{},",
+            t,
+            source,
+            status.block,
+            status.jamming_state,
+            status.jam_ind,
+            status.agc_cnt,
+            status.noise_per_ms
+        );
+        let _ = self.w.flush();
+    }
+
+    fn log_security(&mut self, t: Epoch, security: &SignalSecurity) {
+        let _ = writeln!(
+            self.w,
+            "{},SEC-SIG,,{},,,,{}",
+            t, security.jamming_state, security.spoofing_state
+        );
+        let _ = self.w.flush();
+    }
+}
+
+/// Jamming and spoofing [Monitor]: tracks the interference states
+/// and reports their transitions.
+pub struct Monitor {
+    /// CW jamming indicator threshold
+    jam_threshold: u8,
+    /// Jamming state, per source and RF block
+    jammed: BTreeMap<(String, u8), bool>,
+    /// Jamming state, as reported by SEC-SIG
+    sec_jammed: bool,
+    /// Spoofing state
+    spoofed: bool,
+    /// Time series, if any
+    log: Option<MonitorLog>,
+}
+
+impl Monitor {
+    /// Creates a new [Monitor]
+    pub fn new(jam_threshold: u8, log: Option<MonitorLog>) -> Self {
+        Self {
+            jam_threshold,
+            log,
+            jammed: BTreeMap::new(),
+            sec_jammed: false,
+            spoofed: false,
+        }
+    }
+
+    /// Updates the [RfStatus] of one RF block. Returns a comment
+    /// when jamming is detected, or has stopped.
+    pub fn update_rf(&mut self, t: Epoch, source: &str, status: &RfStatus) -> Option<String> {
+        if let Some(log) = self.log.as_mut() {
+            log.log_rf(t, source, status);
+        }
+
+        let jammed = status.jamming_state >= 2 || status.jam_ind >= self.jam_threshold;
+        let previous = self
+            .jammed
+            .insert((source.to_string(), status.block), jammed)
+            .unwrap_or_default();
+
+        match (previous, jammed) {
+            (false, true) => Some(format!(
+                "JAMMING DETECTED {} RF#{} STATE={} JAMIND={}",
+                source, status.block, status.jamming_state, status.jam_ind
+            )),
+            (true, false) => Some(format!("JAMMING ENDED {} RF#{}", source, status.block)),
+            _ => None,
+        }
+    }
+
+    /// Updates the [SignalSecurity] status. Returns the comments
+    /// when jamming or spoofing is detected, or has stopped.
+    pub fn update_security(&mut self, t: Epoch, security: &SignalSecurity) -> Vec<String> {
+        if let Some(log) = self.log.as_mut() {
+            log.log_security(t, security);
+        }
+
+        let mut comments = Vec::new();
+
+        let jammed = security.jamming_state >= 2;
+        let spoofed = security.spoofing_state >= 2;
+
+        if jammed != self.sec_jammed {
+            comments.push(if jammed {
+                format!("JAMMING DETECTED STATE={}", security.jamming_state)
+            } else {
+                "JAMMING ENDED".to_string()
+            });
+        }
+
+        if spoofed != self.spoofed {
+            comments.push(if spoofed {
+                format!("SPOOFING DETECTED STATE={}", security.spoofing_state)
+            } else {
+                "SPOOFING ENDED".to_string()
+            });
+        }
+
+        self.sec_jammed = jammed;
+        self.spoofed = spoofed;
+
+        comments
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Monitor, RfStatus, SignalSecurity};
+    use hifitime::prelude::Epoch;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_mon_rf() {
+        let mut payload = vec![0, 2, 0, 0];
+
+        for (block, jamming_state, jam_ind) in [(0u8, 1u8, 12u8), (1, 3, 200)] {
+            let mut data = [0u8; 24];
+            data[0] = block;
+            data[1] = jamming_state;
+            data[12..14].copy_from_slice(&87u16.to_le_bytes());
+            data[14..16].copy_from_slice(&4500u16.to_le_bytes());
+            data[16] = jam_ind;
+            payload.extend_from_slice(&data);
+        }
+
+        let blocks = RfStatus::decode_mon_rf(&payload);
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].block, 1);
+        assert_eq!(blocks[1].jamming_state, 3);
+        assert_eq!(blocks[1].jam_ind, 200);
+        assert_eq!(blocks[0].noise_per_ms, 87);
+        assert_eq!(blocks[0].agc_cnt, 4500);
+    }
+
+    #[test]
+    fn test_mon_hw() {
+        // jammingState=2 (warning), antenna power and status bits set
+        let status = RfStatus::from_mon_hw(0x0b, 35, 4500, 87);
+
+        assert_eq!(status.block, 0);
+        assert_eq!(status.jamming_state, 2);
+        assert_eq!(status.jam_ind, 35);
+        assert_eq!(status.agc_cnt, 4500);
+        assert_eq!(status.noise_per_ms, 87);
+    }
+
+    #[test]
+    fn test_monitor_sources() {
+        let t = Epoch::from_str("2025-02-25T20:30:30 GPST").unwrap();
+        let mut monitor = Monitor::new(100, None);
+
+        // MON-HW reports jamming on the first RF block, MON-RF does not:
+        // each source is tracked on its own and does not toggle the other
+        let hw = RfStatus::from_mon_hw(0x08, 20, 4500, 87);
+
+        let rf = RfStatus {
+            jamming_state: 1,
+            jam_ind: 20,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            monitor.update_rf(t, "MON-HW", &hw),
+            Some("JAMMING DETECTED MON-HW RF#0 STATE=2 JAMIND=20".to_string())
+        );
+
+        for _ in 0..3 {
+            assert!(monitor.update_rf(t, "MON-RF", &rf).is_none());
+            assert!(monitor.update_rf(t, "MON-HW", &hw).is_none());
+        }
+
+        let hw = RfStatus::from_mon_hw(0x04, 20, 4500, 87);
+
+        assert_eq!(
+            monitor.update_rf(t, "MON-HW", &hw),
+            Some("JAMMING ENDED MON-HW RF#0".to_string())
+        );
+
+        assert!(monitor.update_rf(t, "MON-RF", &rf).is_none());
+    }
+
+    #[test]
+    fn test_sec_sig() {
+        // version 1: jammingState=1 (ok), spoofingState=2 (indicated)
+        let payload = [1, 0, 0, 0, 0x03, 0, 0, 0, 0x05, 0, 0, 0];
+
+        assert_eq!(
+            SignalSecurity::decode(&payload),
+            Some(SignalSecurity {
+                jamming_state: 1,
+                spoofing_state: 2,
+            })
+        );
+
+        // version 2: jammingState=2 (warning), spoofingState=1 (no spoofing)
+        let payload = [2, 0, 0, 0, 0x1d, 0, 0, 0];
+
+        assert_eq!(
+            SignalSecurity::decode(&payload),
+            Some(SignalSecurity {
+                jamming_state: 2,
+                spoofing_state: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_monitor() {
+        let t = Epoch::from_str("2025-02-25T20:30:30 GPST").unwrap();
+        let mut monitor = Monitor::new(100, None);
+
+        let mut status = RfStatus {
+            jamming_state: 1,
+            jam_ind: 20,
+            ..Default::default()
+        };
+
+        assert!(monitor.update_rf(t, "MON-RF", &status).is_none());
+
+        status.jam_ind = 150;
+
+        assert_eq!(
+            monitor.update_rf(t, "MON-RF", &status),
+            Some("JAMMING DETECTED MON-RF RF#0 STATE=1 JAMIND=150".to_string())
+        );
+
+        assert!(monitor.update_rf(t, "MON-RF", &status).is_none());
+
+        status.jam_ind = 20;
+
+        assert_eq!(
+            monitor.update_rf(t, "MON-RF", &status),
+            Some("JAMMING ENDED MON-RF RF#0".to_string())
+        );
+
+        let security = SignalSecurity {
+            jamming_state: 1,
+            spoofing_state: 3,
+        };
+
+        assert_eq!(
+            monitor.update_security(t, &security),
+            vec!["SPOOFING DETECTED STATE=3".to_string()]
+        );
+
+        assert!(monitor.update_security(t, &security).is_empty());
+    }
+}
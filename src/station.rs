@@ -0,0 +1,129 @@
+use serde::Deserialize;
+use std::{fs::read_to_string, path::Path};
+
+/// Station marker description
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StationMarker {
+    /// Marker name
+    pub name: Option<String>,
+    /// Marker number (DOMES or other)
+    pub number: Option<String>,
+    /// Marker type, like "GEODETIC"
+    #[serde(rename = "type")]
+    pub marker_type: Option<String>,
+}
+
+/// Station antenna description
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StationAntenna {
+    /// Antenna model
+    pub model: String,
+    /// Antenna serial number
+    pub sn: Option<String>,
+    /// Height (ARP) above marker [m]
+    pub height: Option<f64>,
+    /// Eccentricity (East component) to marker [m]
+    pub eastern: Option<f64>,
+    /// Eccentricity (North component) to marker [m]
+    pub northern: Option<f64>,
+}
+
+/// Station receiver description
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StationReceiver {
+    /// Receiver model
+    pub model: Option<String>,
+}
+
+/// [Station] profile, describing one of your sites,
+/// loaded from a TOML or JSON file.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Station {
+    /// Name used in file names
+    pub name: Option<String>,
+    /// Country code (3 letter)
+    pub country: Option<String>,
+    /// Agency
+    pub agency: Option<String>,
+    /// Observer
+    pub observer: Option<String>,
+    /// Operator
+    pub operator: Option<String>,
+    /// [StationMarker]
+    pub marker: Option<StationMarker>,
+    /// [StationAntenna]
+    pub antenna: Option<StationAntenna>,
+    /// [StationReceiver]
+    pub receiver: Option<StationReceiver>,
+}
+
+impl Station {
+    /// Loads [Station] profile from file. JSON is expected
+    /// for .json files, TOML for any other extension.
+    pub fn from_file(path: &str) -> Self {
+        let content = read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read station file \"{}\": {}", path, e));
+
+        let is_json = Path::new(path)
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("json"))
+            .unwrap_or(false);
+
+        if is_json {
+            serde_json::from_str(&content)
+                .unwrap_or_else(|e| panic!("Invalid station file \"{}\": {}", path, e))
+        } else {
+            toml::from_str(&content)
+                .unwrap_or_else(|e| panic!("Invalid station file \"{}\": {}", path, e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Station;
+
+    #[test]
+    fn test_toml_station() {
+        let station: Station = toml::from_str(
+            r#"
+name = "MLVL"
+country = "FRA"
+agency = "IGN"
+observer = "Myself"
+
+[marker]
+name = "MLVL00FRA"
+number = "10004M006"
+type = "GEODETIC"
+
+[antenna]
+model = "TRM29659.00     NONE"
+sn = "0220173011"
+height = 0.0935
+
+[receiver]
+model = "ZED-F9P"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(station.name.as_deref(), Some("MLVL"));
+        assert_eq!(station.country.as_deref(), Some("FRA"));
+        assert_eq!(station.agency.as_deref(), Some("IGN"));
+        assert_eq!(station.observer.as_deref(), Some("Myself"));
+        assert!(station.operator.is_none());
+
+        let marker = station.marker.unwrap();
+        assert_eq!(marker.number.as_deref(), Some("10004M006"));
+        assert_eq!(marker.marker_type.as_deref(), Some("GEODETIC"));
+
+        let antenna = station.antenna.unwrap();
+        assert_eq!(antenna.model, "TRM29659.00     NONE");
+        assert_eq!(antenna.height, Some(0.0935));
+        assert!(antenna.eastern.is_none());
+
+        let receiver = station.receiver.unwrap();
+        assert_eq!(receiver.model.as_deref(), Some("ZED-F9P"));
+    }
+}
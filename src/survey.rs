@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+
+/// Surveyed receiver position, that we may persist
+/// so following sessions start with a known position.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SurveyedPosition {
+    /// ECEF coordinates [m]
+    pub ecef_m: (f64, f64, f64),
+    /// Accuracy [m]
+    pub accuracy_m: f64,
+}
+
+impl SurveyedPosition {
+    /// Loads [SurveyedPosition] from a JSON file, if it exists
+    pub fn from_file(path: &str) -> Option<Self> {
+        if !Path::new(path).exists() {
+            return None;
+        }
+
+        let content = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read \"{}\": {}", path, e));
+
+        let position = serde_json::from_str::<Self>(&content)
+            .unwrap_or_else(|e| panic!("Invalid position file \"{}\": {}", path, e));
+
+        info!(
+            "known position: x={:.3}m y={:.3}m z={:.3}m (accuracy {:.3}m)",
+            position.ecef_m.0, position.ecef_m.1, position.ecef_m.2, position.accuracy_m
+        );
+
+        Some(position)
+    }
+
+    /// Stores [SurveyedPosition] as JSON file
+    pub fn to_file(&self, path: &str) {
+        let content = serde_json::to_string_pretty(self)
+            .unwrap_or_else(|e| panic!("Position serialization: {}", e));
+
+        if let Err(e) = std::fs::write(path, content) {
+            error!("Failed to write \"{}\": {}", path, e);
+        }
+    }
+}
+
+/// Survey-in status, as reported by UBX-NAV-SVIN
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SurveyIn {
+    /// Survey duration [s]
+    pub duration_s: u32,
+    /// Mean position
+    pub position: SurveyedPosition,
+    /// Number of observations
+    pub observations: u32,
+    /// Survey-in position is valid
+    pub valid: bool,
+    /// Survey-in is in progress
+    pub active: bool,
+}
+
+impl SurveyIn {
+    /// Decodes UBX-NAV-SVIN payload
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        if payload.len() < 40 {
+            return None;
+        }
+
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                payload[offset],
+                payload[offset + 1],
+                payload[offset + 2],
+                payload[offset + 3],
+            ])
+        };
+
+        // cm + 0.1mm high precision components
+        let mean_m = |offset: usize, hp: usize| {
+            u32_at(offset) as i32 as f64 * 1.0E-2 + payload[hp] as i8 as f64 * 1.0E-4
+        };
+
+        Some(Self {
+            duration_s: u32_at(8),
+            position: SurveyedPosition {
+                ecef_m: (mean_m(12, 24), mean_m(16, 25), mean_m(20, 26)),
+                accuracy_m: u32_at(28) as f64 * 1.0E-4,
+            },
+            observations: u32_at(32),
+            valid: payload[36] == 1,
+            active: payload[37] == 1,
+        })
+    }
+}
+
+/// [Survey] averages the navigation solutions,
+/// until the mean position reaches the desired accuracy.
+#[derive(Debug, Clone)]
+pub struct Survey {
+    /// Desired accuracy [m]
+    target_m: f64,
+    /// Minimal number of solutions
+    min_samples: u32,
+    /// Number of solutions
+    count: u32,
+    /// Mean position (ECEF [m])
+    mean: (f64, f64, f64),
+    /// Sum of squared deviations (ECEF [m²])
+    m2: (f64, f64, f64),
+    /// Survey has completed
+    completed: bool,
+}
+
+impl Survey {
+    /// Creates a new [Survey] with desired accuracy (in meters)
+    pub fn new(target_m: f64, min_samples: u32) -> Self {
+        Self {
+            target_m,
+            min_samples: min_samples.max(2),
+            count: 0,
+            mean: Default::default(),
+            m2: Default::default(),
+            completed: false,
+        }
+    }
+
+    /// Accuracy of the mean position [m]: 3D standard deviation
+    /// of the solutions, divided by the square root of their number.
+    pub fn accuracy(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+
+        let n = self.count as f64;
+        let var = (self.m2.0 + self.m2.1 + self.m2.2) / (n - 1.0);
+
+        (var / n).sqrt()
+    }
+
+    /// Adds a new solution (ECEF [m]). Returns the [SurveyedPosition]
+    /// once, when the desired accuracy is reached.
+    pub fn add(&mut self, ecef_m: (f64, f64, f64)) -> Option<SurveyedPosition> {
+        if self.completed {
+            return None;
+        }
+
+        self.count += 1;
+        let n = self.count as f64;
+
+        let delta = (
+            ecef_m.0 - self.mean.0,
+            ecef_m.1 - self.mean.1,
+            ecef_m.2 - self.mean.2,
+        );
+
+        self.mean.0 += delta.0 / n;
+        self.mean.1 += delta.1 / n;
+        self.mean.2 += delta.2 / n;
+
+        self.m2.0 += delta.0 * (ecef_m.0 - self.mean.0);
+        self.m2.1 += delta.1 * (ecef_m.1 - self.mean.1);
+        self.m2.2 += delta.2 * (ecef_m.2 - self.mean.2);
+
+        let accuracy_m = self.accuracy();
+
+        if self.count < self.min_samples || accuracy_m > self.target_m {
+            return None;
+        }
+
+        self.completed = true;
+
+        Some(SurveyedPosition {
+            ecef_m: self.mean,
+            accuracy_m,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Survey;
+
+    #[test]
+    fn test_survey() {
+        let mut survey = Survey::new(0.5, 10);
+
+        let (x0, y0, z0) = (4_696_989.0, 723_994.0, 4_239_678.0);
+
+        for i in 0..9 {
+            let dx = if i % 2 == 0 { 1.0 } else { -1.0 };
+            assert!(survey.add((x0 + dx, y0, z0)).is_none());
+        }
+
+        let mut position = None;
+
+        for i in 9..100 {
+            let dx = if i % 2 == 0 { 1.0 } else { -1.0 };
+
+            if let Some(surveyed) = survey.add((x0 + dx, y0, z0)) {
+                position = Some(surveyed);
+                break;
+            }
+        }
+
+        let position = position.expect("survey did not complete");
+
+        assert!(position.accuracy_m <= 0.5);
+        assert!((position.ecef_m.0 - x0).abs() < 0.1);
+        assert!(survey.add((x0, y0, z0)).is_none());
+    }
+}
@@ -0,0 +1,383 @@
+use log::{info, warn};
+
+use rinex::prelude::{Duration, Epoch, TimeScale};
+
+/// Number of milliseconds in one week
+const WEEK_MS: u32 = 604_800_000;
+
+/// GPS time solution, as reported by UBX-NAV-TIMEGPS
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GpsTime {
+    /// GPS week
+    pub week: u32,
+    /// Time of week [ms]
+    pub itow_ms: u32,
+    /// Time of week is valid
+    pub tow_valid: bool,
+    /// Week number is valid
+    pub week_valid: bool,
+}
+
+impl GpsTime {
+    /// Decodes UBX-NAV-TIMEGPS payload
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        if payload.len() < 16 {
+            return None;
+        }
+
+        let week = i16::from_le_bytes([payload[8], payload[9]]);
+
+        Some(Self {
+            week: week.max(0) as u32,
+            itow_ms: u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]),
+            tow_valid: payload[11] & 0x01 > 0,
+            week_valid: payload[11] & 0x02 > 0,
+        })
+    }
+}
+
+/// Resolves the GPS week of this time of week [ms], from a reference week
+/// and time of week [ms]: handles messages on either side of a week rollover.
+fn resolve_week(week: u32, ref_tow_ms: u32, itow_ms: u32) -> u32 {
+    let half_week = WEEK_MS / 2;
+
+    if itow_ms + half_week < ref_tow_ms {
+        // rolled over, reference is not updated yet
+        week + 1
+    } else if ref_tow_ms + half_week < itow_ms && week > 0 {
+        // late message, from previous week
+        week - 1
+    } else {
+        week
+    }
+}
+
+/// Leap second information, as reported by UBX-NAV-TIMELS
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LeapSecondInfo {
+    /// Current leap seconds (GPST - UTC) [s]
+    pub current: i8,
+    /// Current leap seconds are valid
+    pub current_valid: bool,
+    /// Upcoming leap second change [s]
+    pub change: i8,
+    /// Time to the leap second event [s]
+    pub time_to_event_s: i32,
+    /// Time to the leap second event is valid
+    pub event_valid: bool,
+}
+
+impl LeapSecondInfo {
+    /// Decodes UBX-NAV-TIMELS payload
+    pub fn decode(payload: &[u8]) -> Option<Self> {
+        if payload.len() < 24 {
+            return None;
+        }
+
+        Some(Self {
+            current: payload[9] as i8,
+            change: payload[11] as i8,
+            time_to_event_s: i32::from_le_bytes([
+                payload[12],
+                payload[13],
+                payload[14],
+                payload[15],
+            ]),
+            current_valid: payload[23] & 0x01 > 0,
+            event_valid: payload[23] & 0x02 > 0,
+        })
+    }
+}
+
+/// [TimeKeeper] tracks the receiver time: its validity, the GPS week
+/// and the leap seconds. We do not timestamp anything until receiver time is valid.
+#[derive(Debug, Clone, Default)]
+pub struct TimeKeeper {
+    /// Time of week is valid
+    tow_valid: bool,
+    /// Week number is valid
+    week_valid: bool,
+    /// Latest GPS week and time of week [ms]
+    week: Option<(u32, u32)>,
+    /// Current leap seconds (GPST - UTC) [s]
+    leap_seconds: Option<i8>,
+    /// Upcoming leap second event (in GPST) and change [s]
+    leap_event: Option<(Epoch, i8)>,
+}
+
+impl TimeKeeper {
+    /// Updates the receiver time validity (and valid UTC time), from NAV-TIMEUTC
+    pub fn update_utc(&mut self, t_utc: Option<Epoch>, tow_valid: bool, week_valid: bool) {
+        if tow_valid && week_valid && !self.is_valid() {
+            info!("receiver time is now valid");
+        } else if self.is_valid() && !(tow_valid && week_valid) {
+            warn!("receiver time is no longer valid");
+        }
+
+        self.tow_valid = tow_valid;
+        self.week_valid = week_valid;
+
+        if let Some(t_utc) = t_utc {
+            if week_valid {
+                let (week, tow_nanos) = t_utc.to_time_scale(TimeScale::GPST).to_time_of_week();
+                self.week = Some((week, (tow_nanos / 1_000_000) as u32));
+            }
+        }
+    }
+
+    /// Updates receiver time, from NAV-TIMEGPS
+    pub fn update_gps_time(&mut self, gps_time: &GpsTime) {
+        self.tow_valid = gps_time.tow_valid;
+        self.week_valid = gps_time.week_valid;
+
+        if self.is_valid() {
+            self.week = Some((gps_time.week, gps_time.itow_ms));
+        }
+    }
+
+    /// Updates the GPS week and time of week [ms], from the receiver measurements
+    pub fn update_week(&mut self, week: u32, tow_ms: u32) {
+        if self.week_valid {
+            self.week = Some((week, tow_ms));
+        }
+    }
+
+    /// Updates the current leap seconds (GPST - UTC)
+    pub fn set_leap_seconds(&mut self, leap_seconds: i8) {
+        if self.leap_seconds != Some(leap_seconds) {
+            info!("leap seconds (GPST - UTC): {}s", leap_seconds);
+        }
+
+        self.leap_seconds = Some(leap_seconds);
+    }
+
+    /// Updates from NAV-TIMELS, at this [Epoch] (in GPST)
+    pub fn update_leap(&mut self, t_gpst: Epoch, info: &LeapSecondInfo) {
+        if info.current_valid {
+            self.set_leap_seconds(info.current);
+        }
+
+        if info.event_valid && info.change != 0 {
+            let t_event = t_gpst + Duration::from_seconds(info.time_to_event_s as f64);
+
+            if self.leap_event.map(|(t, _)| t) != Some(t_event) {
+                info!("upcoming leap second: {:+}s at {}", info.change, t_event);
+            }
+
+            self.leap_event = Some((t_event, info.change));
+        } else {
+            self.leap_event = None;
+        }
+    }
+
+    /// Returns true once receiver time (time of week and week number) is valid
+    pub fn is_valid(&self) -> bool {
+        self.tow_valid && self.week_valid
+    }
+
+    /// Returns current GPS week, once valid
+    pub fn week(&self) -> Option<u32> {
+        if self.week_valid {
+            self.week.map(|(week, _)| week)
+        } else {
+            None
+        }
+    }
+
+    /// Returns latest receiver [Epoch] (in GPST), once valid
+    pub fn gpst(&self) -> Option<Epoch> {
+        let (week, tow_ms) = self.week.filter(|_| self.is_valid())?;
+
+        Some(Epoch::from_time_of_week(
+            week,
+            tow_ms as u64 * 1_000_000,
+            TimeScale::GPST,
+        ))
+    }
+
+    /// Returns the GPST [Epoch] of this receiver time of week [ms], once valid.
+    /// The week is resolved from the latest receiver time, across rollovers.
+    pub fn gpst_from_itow(&self, itow_ms: u32) -> Option<Epoch> {
+        let (week, tow_ms) = self.week.filter(|_| self.is_valid())?;
+
+        Some(Epoch::from_time_of_week(
+            resolve_week(week, tow_ms, itow_ms),
+            itow_ms as u64 * 1_000_000,
+            TimeScale::GPST,
+        ))
+    }
+
+    /// Returns leap seconds (GPST - UTC) at this GPST [Epoch], when known.
+    /// An upcoming leap second applies as soon as its event is reached.
+    pub fn leap_seconds(&self, t_gpst: Epoch) -> Option<i8> {
+        let leap_seconds = self.leap_seconds?;
+
+        match self.leap_event {
+            Some((t_event, change)) if t_gpst >= t_event => Some(leap_seconds + change),
+            _ => Some(leap_seconds),
+        }
+    }
+
+    /// Expresses this GPST [Epoch] in given [TimeScale].
+    /// UTC conversions use the leap seconds reported by the receiver,
+    /// which prevail over our (possibly outdated) leap seconds table.
+    pub fn to_timescale(&self, t_gpst: Epoch, timescale: TimeScale) -> Epoch {
+        let t = t_gpst.to_time_scale(timescale);
+
+        if timescale != TimeScale::UTC {
+            return t;
+        }
+
+        let leap_seconds = match self.leap_seconds(t_gpst) {
+            Some(leap_seconds) => leap_seconds as f64,
+            None => return t,
+        };
+
+        // (TAI - UTC) - (TAI - GPST)
+        let known = match t_gpst.leap_seconds(true) {
+            Some(tai_utc) => tai_utc - 19.0,
+            None => return t,
+        };
+
+        if known != leap_seconds {
+            t + Duration::from_seconds(known - leap_seconds)
+        } else {
+            t
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{resolve_week, GpsTime, LeapSecondInfo, TimeKeeper};
+    use hifitime::prelude::{Duration, Epoch, TimeScale};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_timekeeper() {
+        let mut timekeeper = TimeKeeper::default();
+        assert!(!timekeeper.is_valid());
+        assert!(timekeeper.week().is_none());
+
+        let t_utc = Epoch::from_str("2025-02-25T20:30:00 UTC").unwrap();
+
+        timekeeper.update_utc(Some(t_utc), true, false);
+        assert!(!timekeeper.is_valid());
+
+        timekeeper.update_utc(Some(t_utc), true, true);
+        assert!(timekeeper.is_valid());
+        assert_eq!(timekeeper.week(), Some(2355));
+
+        let t_gpst = t_utc.to_time_scale(TimeScale::GPST);
+
+        // leap seconds agree with our table
+        timekeeper.set_leap_seconds(18);
+        assert_eq!(timekeeper.to_timescale(t_gpst, TimeScale::UTC), t_utc);
+
+        // receiver prevails
+        timekeeper.set_leap_seconds(17);
+
+        assert_eq!(
+            timekeeper.to_timescale(t_gpst, TimeScale::UTC),
+            t_utc + Duration::from_seconds(1.0)
+        );
+
+        // upcoming leap second
+        let info = LeapSecondInfo {
+            current: 17,
+            current_valid: true,
+            change: 1,
+            time_to_event_s: 60,
+            event_valid: true,
+        };
+
+        timekeeper.update_leap(t_gpst, &info);
+
+        assert_eq!(timekeeper.leap_seconds(t_gpst), Some(17));
+
+        let t_event = t_gpst + Duration::from_seconds(60.0);
+        assert_eq!(timekeeper.leap_seconds(t_event), Some(18));
+        assert_eq!(
+            timekeeper.to_timescale(t_event, TimeScale::UTC),
+            t_event.to_time_scale(TimeScale::UTC)
+        );
+    }
+
+    #[test]
+    fn test_week_rollover() {
+        assert_eq!(resolve_week(2355, 100_000, 200_000), 2355);
+
+        // rollover: reference is still in the previous week
+        assert_eq!(resolve_week(2355, 604_799_000, 500), 2356);
+
+        // late message: reference already rolled over
+        assert_eq!(resolve_week(2356, 500, 604_799_000), 2355);
+
+        let mut timekeeper = TimeKeeper::default();
+        assert!(timekeeper.gpst_from_itow(0).is_none());
+
+        timekeeper.update_gps_time(&GpsTime {
+            week: 2355,
+            itow_ms: 604_799_000,
+            tow_valid: true,
+            week_valid: true,
+        });
+
+        assert_eq!(
+            timekeeper.gpst(),
+            Some(Epoch::from_str("2025-03-01T23:59:59 GPST").unwrap())
+        );
+
+        // end of epoch, right after the rollover
+        assert_eq!(
+            timekeeper.gpst_from_itow(0),
+            Some(Epoch::from_str("2025-03-02T00:00:00 GPST").unwrap())
+        );
+
+        // measurements from the new week
+        timekeeper.update_week(2356, 1_000);
+        assert_eq!(timekeeper.week(), Some(2356));
+
+        assert_eq!(
+            timekeeper.gpst_from_itow(604_799_000),
+            Some(Epoch::from_str("2025-03-01T23:59:59 GPST").unwrap())
+        );
+
+        assert_eq!(
+            timekeeper.gpst_from_itow(2_000),
+            Some(Epoch::from_str("2025-03-02T00:00:02 GPST").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_gps_time() {
+        let mut payload = [0u8; 16];
+        payload[0..4].copy_from_slice(&246_630_000u32.to_le_bytes());
+        payload[8..10].copy_from_slice(&2355i16.to_le_bytes());
+        payload[11] = 0x07;
+
+        let gps_time = GpsTime::decode(&payload).unwrap();
+
+        assert_eq!(gps_time.week, 2355);
+        assert_eq!(gps_time.itow_ms, 246_630_000);
+        assert!(gps_time.tow_valid);
+        assert!(gps_time.week_valid);
+    }
+
+    #[test]
+    fn test_leap_second_info() {
+        let mut payload = [0u8; 24];
+        payload[9] = 18;
+        payload[11] = 1;
+        payload[12..16].copy_from_slice(&3600i32.to_le_bytes());
+        payload[23] = 0x03;
+
+        let info = LeapSecondInfo::decode(&payload).unwrap();
+
+        assert_eq!(info.current, 18);
+        assert_eq!(info.change, 1);
+        assert_eq!(info.time_to_event_s, 3600);
+        assert!(info.current_valid);
+        assert!(info.event_valid);
+    }
+}
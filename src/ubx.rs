@@ -1,5 +1,9 @@
+use std::{collections::BTreeMap, str::FromStr};
+
 use rinex::prelude::{Constellation, Duration, Observable, TimeScale};
-use ublox::{cfg_val::CfgVal, CfgLayerSet, CfgValSetBuilder};
+use ublox::{
+    cfg_val::CfgVal, AlignmentToReferenceTime, CfgLayerSet, CfgValSetBuilder, NavClockRef,
+};
 
 #[derive(Debug, Clone)]
 pub struct Settings {
@@ -11,8 +15,10 @@ pub struct Settings {
     pub l5: bool,
     /// Timescale we align to
     pub timescale: TimeScale,
-    /// Sampling [Duration]
-    pub sampling_period: Duration,
+    /// Receiver time reference (CFG-RATE), which may be GLONASST
+    pub time_ref: AlignmentToReferenceTime,
+    /// Measurement [Duration]
+    pub measurement_period: Duration,
     /// Rawxm enable
     pub rawxm: bool,
     /// Ephemeris enable
@@ -25,14 +31,228 @@ pub struct Settings {
     pub sn: Option<String>,
     /// RX-clock enabled
     pub rx_clock: bool,
+    /// Time marks (TIM-TM2) enabled
+    pub timemark: bool,
+    /// Time pulse data (TIM-TP) enabled
+    pub timepulse: bool,
     /// RX model
     pub model: Option<String>,
     /// Firmware version
     pub firmware: Option<String>,
+    /// Receiver only supports the legacy configuration messages
+    /// (CFG-GNSS, CFG-NAV5..), like M8 series.
+    pub legacy_cfg: bool,
+    /// Abort on configuration mismatch
+    pub strict_cfg: bool,
+    /// Non volatile layers (BBR, Flash) the configuration is saved to
+    pub save_layers: CfgLayerSet,
+    /// Dynamic platform model (user profile)
+    pub dyn_model: Option<DynamicModel>,
+    /// Navigation elevation mask [°]
+    pub min_elev_deg: Option<i8>,
+    /// Navigation C/N0 mask [dB.Hz]
+    pub min_cno_dbhz: Option<u8>,
+    /// Jamming and spoofing monitoring enabled
+    pub anti_spoofing: bool,
+}
+
+/// Dynamic platform model, selected by user profile
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DynamicModel {
+    Portable,
+    Stationary,
+    Pedestrian,
+    Automotive,
+    Sea,
+    Airborne1g,
+    Airborne2g,
+    Airborne4g,
+    Wrist,
+    Bike,
+}
+
+impl FromStr for DynamicModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace(['<', '>'], "").as_str() {
+            "portable" => Ok(Self::Portable),
+            "stationary" => Ok(Self::Stationary),
+            "pedestrian" => Ok(Self::Pedestrian),
+            "automotive" => Ok(Self::Automotive),
+            "sea" => Ok(Self::Sea),
+            "airborne1g" => Ok(Self::Airborne1g),
+            "airborne2g" => Ok(Self::Airborne2g),
+            "airborne4g" => Ok(Self::Airborne4g),
+            "wrist" => Ok(Self::Wrist),
+            "bike" => Ok(Self::Bike),
+            _ => Err(format!("unknown profile \"{}\"", s)),
+        }
+    }
+}
+
+impl DynamicModel {
+    /// Returns the CFG-NAVSPG-DYNMODEL (or CFG-NAV5 dynModel) value
+    pub fn value(&self) -> u8 {
+        match self {
+            Self::Portable => 0,
+            Self::Stationary => 2,
+            Self::Pedestrian => 3,
+            Self::Automotive => 4,
+            Self::Sea => 5,
+            Self::Airborne1g => 6,
+            Self::Airborne2g => 7,
+            Self::Airborne4g => 8,
+            Self::Wrist => 9,
+            Self::Bike => 10,
+        }
+    }
+}
+
+/// Parses the UBX protocol version, from a MON-VER extension
+/// like "PROTVER=18.00" (or "PROTVER 15.00" on older firmwares)
+pub fn parse_protocol_version(extension: &str) -> Option<(u8, u8)> {
+    let version = extension
+        .strip_prefix("PROTVER")?
+        .trim_start_matches(['=', ' '])
+        .trim();
+
+    let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Returns true if this UBX protocol version predates CFG-VALSET (27.00, u-blox 9).
+/// 23.01 is the last u-blox M8 protocol.
+pub fn is_legacy_protocol(version: (u8, u8)) -> bool {
+    version < (27, 0)
+}
+
+/// Returns true if this MON-VER hardware version (hexadecimal, like "00080000")
+/// describes u-blox M8 series (or older) hardware.
+pub fn is_legacy_hardware(version: &str) -> bool {
+    u32::from_str_radix(version.trim_end_matches('\0').trim(), 16)
+        .map(|version| version <= 0x0008_0000)
+        .unwrap_or(false)
 }
 
+/// CFG-GNSS configuration blocks: GNSS identifier, reserved and maximal
+/// tracking channels, signal configuration masks (L1, L2/E5b)
+const CFG_GNSS_BLOCKS: [(Constellation, u8, u8, u8, (u32, u32)); 5] = [
+    (Constellation::GPS, 0, 8, 16, (0x01, 0x10)),
+    (Constellation::Galileo, 2, 4, 8, (0x01, 0x20)),
+    (Constellation::BeiDou, 3, 8, 16, (0x01, 0x10)),
+    (Constellation::QZSS, 5, 0, 3, (0x01, 0x10)),
+    (Constellation::Glonass, 6, 8, 14, (0x01, 0x10)),
+];
+
 impl Settings {
-    pub fn to_ram_volatile_cfg(&self, buf: &mut Vec<u8>) {
+    /// Builds the legacy UBX-CFG-GNSS frame (M8 series),
+    /// that selects the constellations and signals.
+    pub fn to_legacy_cfg_gnss(&self) -> Vec<u8> {
+        let mut payload = vec![0, 0, 0xff, CFG_GNSS_BLOCKS.len() as u8];
+
+        for (constellation, gnss_id, res_trk_ch, max_trk_ch, signals) in CFG_GNSS_BLOCKS {
+            let mut mask = 0;
+
+            if self.l1 {
+                mask |= signals.0;
+            }
+
+            if self.l2 {
+                mask |= signals.1;
+            }
+
+            let mut flags = mask << 16;
+
+            if mask > 0 && self.constellations.contains(&constellation) {
+                flags |= 0x01;
+            }
+
+            payload.extend_from_slice(&[gnss_id, res_trk_ch, max_trk_ch, 0]);
+            payload.extend_from_slice(&flags.to_le_bytes());
+        }
+
+        ubx_frame(CFG_GNSS.0, CFG_GNSS.1, &payload)
+    }
+
+    /// Configuration layers: RAM, and possibly the non volatile layers
+    pub fn layers(&self) -> CfgLayerSet {
+        CfgLayerSet::RAM | self.save_layers
+    }
+
+    /// Navigation settings (dynamic model, masks), as CFG-VALSET items
+    pub fn nav_cfg_items(&self) -> Vec<(u32, Vec<u8>)> {
+        let mut items = Vec::new();
+
+        if let Some(dyn_model) = self.dyn_model {
+            items.push((CFG_NAVSPG_DYNMODEL, vec![dyn_model.value()]));
+        }
+
+        if let Some(min_elev) = self.min_elev_deg {
+            items.push((CFG_NAVSPG_INFIL_MINELEV, vec![min_elev as u8]));
+        }
+
+        if let Some(min_cno) = self.min_cno_dbhz {
+            items.push((CFG_NAVSPG_INFIL_MINCNO, vec![min_cno]));
+        }
+
+        items
+    }
+
+    /// Interference monitor settings, as CFG-VALSET items
+    pub fn monitor_cfg_items(&self) -> Vec<(u32, Vec<u8>)> {
+        if self.anti_spoofing {
+            vec![(CFG_ITFM_ENABLE, vec![1])]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Builds the legacy UBX-CFG-ITFM frame (M8 series), that enables
+    /// the interference monitor with recommended thresholds, if requested.
+    pub fn to_legacy_cfg_itfm(&self) -> Option<Vec<u8>> {
+        if !self.anti_spoofing {
+            return None;
+        }
+
+        // bbThreshold=3, cwThreshold=15, algorithmBits, enable
+        let config: u32 = 0x8000_0000 | (0x16b156 << 9) | (15 << 4) | 3;
+        // generalBits, antSetting=unknown
+        let config2: u32 = 0x31e;
+
+        let mut payload = config.to_le_bytes().to_vec();
+        payload.extend_from_slice(&config2.to_le_bytes());
+
+        Some(ubx_frame(CFG_ITFM.0, CFG_ITFM.1, &payload))
+    }
+
+    /// Builds the legacy UBX-CFG-NAV5 frame (M8 series),
+    /// that applies the dynamic model and elevation mask, if any.
+    pub fn to_legacy_cfg_nav5(&self) -> Option<Vec<u8>> {
+        if self.dyn_model.is_none() && self.min_elev_deg.is_none() {
+            return None;
+        }
+
+        let mut payload = [0u8; 36];
+        let mut mask = 0u16;
+
+        if let Some(dyn_model) = self.dyn_model {
+            mask |= 0x0001;
+            payload[2] = dyn_model.value();
+        }
+
+        if let Some(min_elev) = self.min_elev_deg {
+            mask |= 0x0002;
+            payload[12] = min_elev as u8;
+        }
+
+        payload[..2].copy_from_slice(&mask.to_le_bytes());
+
+        Some(ubx_frame(CFG_NAV5.0, CFG_NAV5.1, &payload))
+    }
+
+    pub fn to_valset_cfg(&self, buf: &mut Vec<u8>) {
         let mut cfg_data = Vec::<CfgVal>::new();
 
         if self.constellations.contains(&Constellation::GPS)
@@ -105,10 +325,558 @@ impl Settings {
 
         CfgValSetBuilder {
             version: 0,
-            layers: CfgLayerSet::RAM,
+            layers: self.layers(),
             reserved1: 0,
             cfg_data: &cfg_data,
         }
         .extend_to(buf);
     }
 }
+
+/// UBX-CFG-VALSET class and message ID
+pub const CFG_VALSET: (u8, u8) = (0x06, 0x8a);
+
+/// UBX-CFG-MSG class and message ID
+pub const CFG_MSG: (u8, u8) = (0x06, 0x01);
+
+/// UBX-CFG-CFG class and message ID
+pub const CFG_CFG: (u8, u8) = (0x06, 0x09);
+
+/// UBX-CFG-RST class and message ID
+pub const CFG_RST: (u8, u8) = (0x06, 0x04);
+
+/// CFG-CFG mask, that selects all configuration sections
+pub const CFG_CFG_ALL: u32 = 0x0000_1f1f;
+
+/// UBX-CFG-NAV5 class and message ID
+pub const CFG_NAV5: (u8, u8) = (0x06, 0x24);
+
+/// UBX-CFG-ITFM class and message ID
+pub const CFG_ITFM: (u8, u8) = (0x06, 0x39);
+
+/// CFG-ITFM-ENABLE
+pub const CFG_ITFM_ENABLE: u32 = 0x1041000d;
+
+/// UBX-CFG-NAVX5 class and message ID
+pub const CFG_NAVX5: (u8, u8) = (0x06, 0x23);
+
+/// CFG-NAVX5 minCNO offset
+pub const CFG_NAVX5_MIN_CNO_OFFSET: usize = 12;
+
+/// CFG-NAVSPG-DYNMODEL
+pub const CFG_NAVSPG_DYNMODEL: u32 = 0x20110021;
+
+/// CFG-NAVSPG-INFIL_MINELEV [°]
+pub const CFG_NAVSPG_INFIL_MINELEV: u32 = 0x201100a4;
+
+/// CFG-NAVSPG-INFIL_MINCNO [dB.Hz]
+pub const CFG_NAVSPG_INFIL_MINCNO: u32 = 0x201100a3;
+
+/// UBX-CFG-VALGET class and message ID
+pub const CFG_VALGET: (u8, u8) = (0x06, 0x8b);
+
+/// CFG-VALGET: maximal number of items per message
+pub const CFG_VALGET_MAX_ITEMS: usize = 64;
+
+/// CFG-VALGET key that selects all configuration items
+/// (group and item ID wildcards)
+pub const CFG_KEY_ALL: u32 = 0x0fffffff;
+
+/// CFG-RATE-MEAS [ms]
+pub const CFG_RATE_MEAS: u32 = 0x30210001;
+
+/// CFG-RATE-NAV
+pub const CFG_RATE_NAV: u32 = 0x30210002;
+
+/// CFG-RATE-TIMEREF
+pub const CFG_RATE_TIMEREF: u32 = 0x20210003;
+
+/// UBX-CFG-GNSS class and message ID
+pub const CFG_GNSS: (u8, u8) = (0x06, 0x3e);
+
+/// UBX-CFG-TMODE2 class and message ID (u-blox M8 timing receivers)
+pub const CFG_TMODE2: (u8, u8) = (0x06, 0x3d);
+
+/// UBX-NAV-SVIN class and message ID
+pub const NAV_SVIN: (u8, u8) = (0x01, 0x3b);
+
+/// UBX-MON-RF class and message ID
+pub const MON_RF: (u8, u8) = (0x0a, 0x38);
+
+/// UBX-SEC-SIG class and message ID
+pub const SEC_SIG: (u8, u8) = (0x27, 0x09);
+
+/// UBX-NAV-TIMEGPS class and message ID
+pub const NAV_TIMEGPS: (u8, u8) = (0x01, 0x20);
+
+/// UBX-NAV-TIMELS class and message ID
+pub const NAV_TIMELS: (u8, u8) = (0x01, 0x26);
+
+/// CFG-TMODE-MODE: 0 (disabled), 1 (survey-in) or 2 (fixed)
+pub const CFG_TMODE_MODE: u32 = 0x20030001;
+
+/// CFG-TMODE-POS_TYPE: 0 (ECEF) or 1 (LLH)
+pub const CFG_TMODE_POS_TYPE: u32 = 0x20030002;
+
+/// CFG-TMODE-ECEF_X/Y/Z [cm]
+pub const CFG_TMODE_ECEF: [u32; 3] = [0x40030003, 0x40030004, 0x40030005];
+
+/// CFG-TMODE-ECEF_X/Y/Z_HP [0.1mm]
+pub const CFG_TMODE_ECEF_HP: [u32; 3] = [0x20030006, 0x20030007, 0x20030008];
+
+/// CFG-TMODE-FIXED_POS_ACC [0.1mm]
+pub const CFG_TMODE_FIXED_POS_ACC: u32 = 0x4003000f;
+
+/// CFG-TMODE-SVIN_MIN_DUR [s]
+pub const CFG_TMODE_SVIN_MIN_DUR: u32 = 0x40030010;
+
+/// CFG-TMODE-SVIN_ACC_LIMIT [0.1mm]
+pub const CFG_TMODE_SVIN_ACC_LIMIT: u32 = 0x40030011;
+
+/// Splits this ECEF coordinate [m] into its CFG-TMODE-ECEF [cm] and high precision
+/// CFG-TMODE-ECEF_HP [0.1mm] components. Both share the same sign, the high
+/// precision component remains within -99..99.
+pub fn tmode_ecef(coord_m: f64) -> (i32, i8) {
+    let total = (coord_m * 1.0E4).round() as i64;
+    ((total / 100) as i32, (total % 100) as i8)
+}
+
+/// Builds a UBX-CFG-TMODE2 frame (u-blox M8 timing receivers): time mode 1 (survey-in)
+/// or 2 (fixed position), fixed ECEF position [cm] and its accuracy [mm],
+/// survey-in minimal duration [s] and accuracy limit [mm].
+pub fn cfg_tmode2_frame(
+    mode: u8,
+    ecef_cm: (i32, i32, i32),
+    fixed_pos_acc_mm: u32,
+    svin_min_dur_s: u32,
+    svin_acc_limit_mm: u32,
+) -> Vec<u8> {
+    // time mode, reserved, flags (ECEF)
+    let mut payload = vec![mode, 0, 0, 0];
+
+    for coord_cm in [ecef_cm.0, ecef_cm.1, ecef_cm.2] {
+        payload.extend_from_slice(&coord_cm.to_le_bytes());
+    }
+
+    payload.extend_from_slice(&fixed_pos_acc_mm.to_le_bytes());
+    payload.extend_from_slice(&svin_min_dur_s.to_le_bytes());
+    payload.extend_from_slice(&svin_acc_limit_mm.to_le_bytes());
+
+    ubx_frame(CFG_TMODE2.0, CFG_TMODE2.1, &payload)
+}
+
+/// Returns the receiver clock bias [s] and drift [s/s], reported by UBX-NAV-CLOCK.
+/// The accessors already scale clkB [ns] and clkD [ns/s].
+pub fn nav_clock_state(pkt: &NavClockRef) -> (f64, f64) {
+    (pkt.clk_bias(), pkt.clk_drift())
+}
+
+/// Builds a complete UBX frame (sync chars, header and checksum),
+/// for messages that we encode ourselves.
+pub fn ubx_frame(class: u8, id: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 8);
+
+    frame.extend_from_slice(&[0xb5, 0x62, class, id]);
+    frame.extend_from_slice(&(payload.len() as u16).to_le_bytes());
+    frame.extend_from_slice(payload);
+
+    let (mut ck_a, mut ck_b) = (0u8, 0u8);
+
+    for byte in frame[2..].iter() {
+        ck_a = ck_a.wrapping_add(*byte);
+        ck_b = ck_b.wrapping_add(ck_a);
+    }
+
+    frame.push(ck_a);
+    frame.push(ck_b);
+    frame
+}
+
+/// Builds a UBX-CFG-VALSET frame, from raw (key, little endian value) pairs
+pub fn cfg_valset_frame(layers: CfgLayerSet, items: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut payload = vec![0, layers.bits(), 0, 0];
+
+    for (key, value) in items.iter() {
+        payload.extend_from_slice(&key.to_le_bytes());
+        payload.extend_from_slice(value);
+    }
+
+    ubx_frame(CFG_VALSET.0, CFG_VALSET.1, &payload)
+}
+
+/// Converts [CfgLayerSet] to CFG-CFG device mask (BBR, Flash)
+pub fn cfg_device_mask(layers: CfgLayerSet) -> u8 {
+    let mut mask = 0;
+
+    if layers.contains(CfgLayerSet::BBR) {
+        mask |= 0x01;
+    }
+
+    if layers.contains(CfgLayerSet::FLASH) {
+        mask |= 0x02;
+    }
+
+    mask
+}
+
+/// Builds a UBX-CFG-CFG frame, that clears, saves and/or loads
+/// the configuration sections, on these devices.
+pub fn cfg_cfg_frame(clear: u32, save: u32, load: u32, device_mask: u8) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(13);
+
+    payload.extend_from_slice(&clear.to_le_bytes());
+    payload.extend_from_slice(&save.to_le_bytes());
+    payload.extend_from_slice(&load.to_le_bytes());
+    payload.push(device_mask);
+
+    ubx_frame(CFG_CFG.0, CFG_CFG.1, &payload)
+}
+
+/// Builds a UBX-CFG-RST frame: controlled software reset and cold start
+pub fn cfg_rst_frame() -> Vec<u8> {
+    ubx_frame(CFG_RST.0, CFG_RST.1, &[0xff, 0xff, 0x01, 0x00])
+}
+
+/// Builds a UBX-CFG-VALGET poll frame (RAM layer), starting at this position
+pub fn cfg_valget_frame(position: u16, keys: &[u32]) -> Vec<u8> {
+    let mut payload = vec![0, 0];
+    payload.extend_from_slice(&position.to_le_bytes());
+
+    for key in keys.iter() {
+        payload.extend_from_slice(&key.to_le_bytes());
+    }
+
+    ubx_frame(CFG_VALGET.0, CFG_VALGET.1, &payload)
+}
+
+/// Returns the value size (in bytes) of this configuration key
+pub fn cfg_value_size(key: u32) -> usize {
+    match (key >> 28) & 0x07 {
+        0x01 | 0x02 => 1,
+        0x03 => 2,
+        0x04 => 4,
+        0x05 => 8,
+        _ => 0,
+    }
+}
+
+/// Parses (key, value) pairs, from CFG-VALSET and CFG-VALGET payloads
+/// (excluding the 4 byte header)
+pub fn parse_cfg_items(data: &[u8]) -> Vec<(u32, Vec<u8>)> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+
+    while offset + 4 <= data.len() {
+        let key = u32::from_le_bytes([
+            data[offset],
+            data[offset + 1],
+            data[offset + 2],
+            data[offset + 3],
+        ]);
+
+        let size = cfg_value_size(key);
+        offset += 4;
+
+        if size == 0 || offset + size > data.len() {
+            break;
+        }
+
+        items.push((key, data[offset..offset + size].to_vec()));
+        offset += size;
+    }
+
+    items
+}
+
+/// Formats a configuration value, as unsigned integer
+pub fn format_cfg_value(value: &[u8]) -> String {
+    let mut bytes = [0u8; 8];
+    bytes[..value.len().min(8)].copy_from_slice(&value[..value.len().min(8)]);
+    let value = u64::from_le_bytes(bytes);
+    format!("{} (0x{:x})", value, value)
+}
+
+/// Compares the requested configuration to the one read back from the receiver.
+/// Returns the mismatches: key, requested value and actual value (if reported).
+pub fn diff_cfg(
+    requested: &[(u32, Vec<u8>)],
+    actual: &[(u32, Vec<u8>)],
+) -> Vec<(u32, Vec<u8>, Option<Vec<u8>>)> {
+    // last value prevails, when a key is set several times
+    let requested = requested
+        .iter()
+        .map(|(key, value)| (*key, value))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut mismatches = Vec::new();
+
+    for (key, value) in requested {
+        let found = actual.iter().find(|(k, _)| *k == key).map(|(_, v)| v);
+
+        if found != Some(value) {
+            mismatches.push((key, value.clone(), found.cloned()));
+        }
+    }
+
+    mismatches
+}
+
+/// Compares the requested CFG-GNSS payload to the one read back from
+/// the receiver. Returns the GNSS identifiers that do not match
+/// (enable flag and signal configuration).
+pub fn diff_legacy_gnss(requested: &[u8], actual: &[u8]) -> Vec<u8> {
+    let blocks = |payload: &[u8]| {
+        payload
+            .get(4..)
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|block| {
+                let flags = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+                (block[0], flags & 0x00ff_0001)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let actual = blocks(actual);
+
+    blocks(requested)
+        .into_iter()
+        .filter(|block| !actual.contains(block))
+        .map(|(gnss_id, _)| gnss_id)
+        .collect()
+}
+
+/// Builds a UBX-CFG-NAVX5 frame that applies this C/N0 mask,
+/// from the current CFG-NAVX5 payload (that defines the message version).
+pub fn cfg_navx5_min_cno_frame(current: &[u8], min_cno_dbhz: u8) -> Vec<u8> {
+    let mut payload = current.to_vec();
+    payload.resize(payload.len().max(40), 0);
+
+    // only minCNO is applied
+    payload[2..8].copy_from_slice(&[0x08, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    payload[CFG_NAVX5_MIN_CNO_OFFSET] = min_cno_dbhz;
+
+    ubx_frame(CFG_NAVX5.0, CFG_NAVX5.1, &payload)
+}
+
+/// Builds a UBX-CFG-MSG frame, that sets the output rate
+/// of this message on all ports.
+pub fn cfg_msg_frame(class: u8, id: u8, rate: u8) -> Vec<u8> {
+    ubx_frame(
+        CFG_MSG.0,
+        CFG_MSG.1,
+        &[class, id, rate, rate, rate, rate, rate, rate],
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        cfg_cfg_frame, cfg_device_mask, cfg_tmode2_frame, cfg_valget_frame, diff_cfg,
+        is_legacy_hardware, is_legacy_protocol, parse_cfg_items, parse_protocol_version,
+        tmode_ecef, ubx_frame, DynamicModel, Settings, CFG_KEY_ALL,
+    };
+    use hifitime::prelude::{Duration, TimeScale};
+    use rinex::prelude::Constellation;
+    use std::str::FromStr;
+    use ublox::{AlignmentToReferenceTime, CfgLayerSet};
+
+    #[test]
+    fn test_ubx_frame() {
+        // MON-VER poll request
+        assert_eq!(
+            ubx_frame(0x0a, 0x04, &[]),
+            vec![0xb5, 0x62, 0x0a, 0x04, 0x00, 0x00, 0x0e, 0x34]
+        );
+    }
+
+    #[test]
+    fn test_protocol_version() {
+        assert_eq!(parse_protocol_version("PROTVER=18.00"), Some((18, 0)));
+        assert_eq!(parse_protocol_version("PROTVER 15.00"), Some((15, 0)));
+        assert_eq!(parse_protocol_version("PROTVER=27.31"), Some((27, 31)));
+        assert_eq!(parse_protocol_version("FWVER=HPG 1.32"), None);
+
+        assert!(is_legacy_protocol((18, 0)));
+        assert!(is_legacy_protocol((23, 0)));
+        assert!(is_legacy_protocol((23, 1)));
+        assert!(!is_legacy_protocol((27, 0)));
+        assert!(!is_legacy_protocol((27, 31)));
+
+        assert!(is_legacy_hardware("00080000\0\0"));
+        assert!(is_legacy_hardware("00070000"));
+        assert!(!is_legacy_hardware("00190000"));
+        assert!(!is_legacy_hardware("000A0000"));
+    }
+
+    #[test]
+    fn test_legacy_cfg_gnss() {
+        let settings = Settings {
+            l1: true,
+            l2: false,
+            l5: false,
+            timescale: TimeScale::GPST,
+            time_ref: AlignmentToReferenceTime::Gps,
+            measurement_period: Duration::from_seconds(30.0),
+            rawxm: true,
+            ephemeris: false,
+            solutions_ratio: 1,
+            constellations: vec![Constellation::GPS, Constellation::Glonass],
+            sn: None,
+            rx_clock: false,
+            timemark: false,
+            timepulse: false,
+            model: None,
+            firmware: None,
+            legacy_cfg: true,
+            strict_cfg: false,
+            save_layers: CfgLayerSet::empty(),
+            dyn_model: None,
+            min_elev_deg: None,
+            min_cno_dbhz: None,
+            anti_spoofing: false,
+        };
+
+        let frame = settings.to_legacy_cfg_gnss();
+
+        // header + 4 bytes + 5 blocks + checksum
+        assert_eq!(frame.len(), 6 + 4 + 5 * 8 + 2);
+        assert_eq!(&frame[2..4], &[0x06, 0x3e]);
+        assert_eq!(frame[9], 5);
+
+        let block = |gnss_id: u8| {
+            let offset = 10
+                + frame[10..]
+                    .chunks(8)
+                    .position(|block| block[0] == gnss_id)
+                    .unwrap()
+                    * 8;
+            u32::from_le_bytes([
+                frame[offset + 4],
+                frame[offset + 5],
+                frame[offset + 6],
+                frame[offset + 7],
+            ])
+        };
+
+        // GPS L1C/A enabled
+        assert_eq!(block(0), 0x0001_0001);
+        // Galileo disabled
+        assert_eq!(block(2), 0x0001_0000);
+        // Glonass L1OF enabled
+        assert_eq!(block(6), 0x0001_0001);
+    }
+
+    #[test]
+    fn test_tmode_ecef() {
+        assert_eq!(tmode_ecef(4027893.1234), (402789312, 34));
+        assert_eq!(tmode_ecef(-4027893.1234), (-402789312, -34));
+
+        // rounding to the next cm is carried
+        assert_eq!(tmode_ecef(1.00996), (101, 0));
+        assert_eq!(tmode_ecef(-1.00996), (-101, 0));
+        assert_eq!(tmode_ecef(1.23999), (124, 0));
+        assert_eq!(tmode_ecef(1.2399), (123, 99));
+    }
+
+    #[test]
+    fn test_cfg_tmode2() {
+        // survey-in: 300 s, 2 m
+        let frame = cfg_tmode2_frame(1, (0, 0, 0), 0, 300, 2000);
+
+        assert_eq!(frame[..6], [0xb5, 0x62, 0x06, 0x3d, 0x1c, 0x00]);
+        assert_eq!(frame[6..10], [1, 0, 0, 0]);
+        assert_eq!(frame[26..30], 300u32.to_le_bytes());
+        assert_eq!(frame[30..34], 2000u32.to_le_bytes());
+
+        // fixed position
+        let frame = cfg_tmode2_frame(2, (402789312, 0, -1), 500, 0, 0);
+        assert_eq!(frame[6], 2);
+        assert_eq!(frame[10..14], 402789312i32.to_le_bytes());
+        assert_eq!(frame[18..22], (-1i32).to_le_bytes());
+        assert_eq!(frame[22..26], 500u32.to_le_bytes());
+        assert_eq!(frame.len(), 6 + 28 + 2);
+    }
+
+    #[test]
+    fn test_cfg_valget_poll() {
+        // poll all items from the RAM layer, second page
+        let frame = cfg_valget_frame(64, &[CFG_KEY_ALL]);
+
+        assert_eq!(frame[..6], [0xb5, 0x62, 0x06, 0x8b, 0x08, 0x00]);
+        assert_eq!(
+            frame[6..14],
+            [0x00, 0x00, 0x40, 0x00, 0xff, 0xff, 0xff, 0x0f]
+        );
+        assert_eq!(frame.len(), 6 + 8 + 2);
+    }
+
+    #[test]
+    fn test_cfg_items() {
+        let frame = cfg_valget_frame(0, &[0x30210001, 0x10310001]);
+        assert_eq!(frame.len(), 6 + 4 + 8 + 2);
+
+        let data = [
+            0x01, 0x00, 0x21, 0x30, 0xe8, 0x03, // CFG-RATE-MEAS: 1000 ms
+            0x01, 0x00, 0x31, 0x10, 0x01, // CFG-SIGNAL-GPS_ENA: true
+            0x03, 0x00, 0x21, 0x20, 0x01, // CFG-RATE-TIMEREF: GPS
+        ];
+
+        let items = parse_cfg_items(&data);
+
+        assert_eq!(
+            items,
+            vec![
+                (0x30210001, vec![0xe8, 0x03]),
+                (0x10310001, vec![0x01]),
+                (0x20210003, vec![0x01]),
+            ]
+        );
+
+        let actual = vec![(0x30210001, vec![0xe8, 0x03]), (0x10310001, vec![0x00])];
+
+        let mismatches = diff_cfg(&items, &actual);
+
+        assert_eq!(
+            mismatches,
+            vec![
+                (0x10310001, vec![0x01], Some(vec![0x00])),
+                (0x20210003, vec![0x01], None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cfg_cfg() {
+        assert_eq!(cfg_device_mask(CfgLayerSet::RAM), 0);
+        assert_eq!(cfg_device_mask(CfgLayerSet::BBR | CfgLayerSet::FLASH), 0x03);
+
+        let frame = cfg_cfg_frame(0, 0x1f1f, 0, 0x01);
+
+        assert_eq!(
+            frame[..19],
+            [
+                0xb5, 0x62, 0x06, 0x09, 0x0d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x1f, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x01
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dynamic_model() {
+        assert_eq!(
+            DynamicModel::from_str("portable"),
+            Ok(DynamicModel::Portable)
+        );
+        assert_eq!(
+            DynamicModel::from_str("Stationary"),
+            Ok(DynamicModel::Stationary)
+        );
+        assert_eq!(
+            DynamicModel::from_str("airborne<2g>"),
+            Ok(DynamicModel::Airborne2g)
+        );
+        assert_eq!(DynamicModel::from_str("bike").unwrap().value(), 10);
+        assert!(DynamicModel::from_str("rocket").is_err());
+    }
+}
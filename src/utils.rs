@@ -1,16 +1,54 @@
 use ublox::AlignmentToReferenceTime;
 
-use rinex::prelude::{Constellation, TimeScale};
-
-// pub fn to_timescale(t_ref: AlignmentToReferenceTime) -> TimeScale {
-//     match t_ref {
-//         AlignmentToReferenceTime::Bds => TimeScale::BDT,
-//         AlignmentToReferenceTime::Gal => TimeScale::GST,
-//         AlignmentToReferenceTime::Gps => TimeScale::GPST,
-//         AlignmentToReferenceTime::Utc => TimeScale::UTC,
-//         AlignmentToReferenceTime::Glo => panic!("GlonassT is not supported yet!"),
-//     }
-// }
+use std::str::FromStr;
+
+use rinex::prelude::{Constellation, Epoch, TimeScale};
+
+/// Speed of light in vacuum [m/s]
+pub const SPEED_OF_LIGHT_M_S: f64 = 299_792_458.0;
+
+/// Converts [AlignmentToReferenceTime] to [TimeScale].
+/// GLONASST is UTC(SU) (without the 3 hour offset, like RINEX does):
+/// it follows UTC and its leap seconds, so we express it as UTC.
+pub fn to_timescale(t_ref: AlignmentToReferenceTime) -> TimeScale {
+    match t_ref {
+        AlignmentToReferenceTime::Bds => TimeScale::BDT,
+        AlignmentToReferenceTime::Gal => TimeScale::GST,
+        AlignmentToReferenceTime::Gps => TimeScale::GPST,
+        AlignmentToReferenceTime::Utc => TimeScale::UTC,
+        AlignmentToReferenceTime::Glo => TimeScale::UTC,
+    }
+}
+
+/// Builds an [Epoch] from a week counter and a time of week (in nanoseconds),
+/// both counted from the GPS origin but expressed in given [TimeScale].
+pub fn from_week_tow(week: u32, tow_nanos: u64, timescale: TimeScale) -> Epoch {
+    let t = Epoch::from_time_of_week(week, tow_nanos, TimeScale::GPST);
+
+    if timescale == TimeScale::GPST {
+        return t;
+    }
+
+    let (y, m, d, hh, mm, ss, nanos) = t.to_gregorian(TimeScale::GPST);
+    Epoch::from_gregorian(y, m, d, hh, mm, ss, nanos, timescale)
+}
+
+/// Parses [AlignmentToReferenceTime] from a timescale description.
+/// Unlike [TimeScale], this supports GLONASST.
+pub fn parse_time_reference(s: &str) -> Result<AlignmentToReferenceTime, String> {
+    match s.trim().to_uppercase().as_str() {
+        "GLO" | "GLONASST" | "GLONASS" | "UTC(SU)" => Ok(AlignmentToReferenceTime::Glo),
+        s => {
+            let ts = TimeScale::from_str(s).map_err(|e| e.to_string())?;
+            match ts {
+                TimeScale::GPST | TimeScale::GST | TimeScale::BDT | TimeScale::UTC => {
+                    Ok(from_timescale(ts))
+                },
+                ts => Err(format!("{} timescale is not supported", ts)),
+            }
+        },
+    }
+}
 
 pub fn from_timescale(ts: TimeScale) -> AlignmentToReferenceTime {
     match ts {
@@ -61,6 +99,27 @@ pub fn from_timescale(ts: TimeScale) -> AlignmentToReferenceTime {
 //     mask
 // }
 
+/// Converts WGS84 geodetic coordinates (latitude [°], longitude [°], altitude [m])
+/// to ECEF coordinates [m].
+pub fn geodetic_to_ecef(lat_ddeg: f64, long_ddeg: f64, alt_m: f64) -> (f64, f64, f64) {
+    const A: f64 = 6_378_137.0;
+    const F: f64 = 1.0 / 298.257_223_563;
+
+    let e2 = F * (2.0 - F);
+
+    let (lat, long) = (lat_ddeg.to_radians(), long_ddeg.to_radians());
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_long, cos_long) = long.sin_cos();
+
+    let n = A / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+
+    (
+        (n + alt_m) * cos_lat * cos_long,
+        (n + alt_m) * cos_lat * sin_long,
+        (n * (1.0 - e2) + alt_m) * sin_lat,
+    )
+}
+
 pub fn to_constellation(id: u8) -> Option<Constellation> {
     match id {
         0 => Some(Constellation::GPS),
@@ -73,6 +132,79 @@ pub fn to_constellation(id: u8) -> Option<Constellation> {
     }
 }
 
+/// Converts u-Blox signal identifier to RINEX (V3) signal code
+pub fn to_signal_code(constellation: Constellation, sig_id: u8) -> Option<&'static str> {
+    match constellation {
+        Constellation::GPS => match sig_id {
+            0 => Some("1C"),
+            3 => Some("2L"),
+            4 => Some("2S"),
+            6 => Some("5I"),
+            7 => Some("5Q"),
+            _ => None,
+        },
+        Constellation::SBAS => match sig_id {
+            0 => Some("1C"),
+            _ => None,
+        },
+        Constellation::Galileo => match sig_id {
+            0 => Some("1C"),
+            1 => Some("1B"),
+            3 => Some("5I"),
+            4 => Some("5Q"),
+            5 => Some("7I"),
+            6 => Some("7Q"),
+            _ => None,
+        },
+        Constellation::BeiDou => match sig_id {
+            0 | 1 => Some("2I"),
+            2 | 3 => Some("7I"),
+            5 => Some("1P"),
+            7 => Some("5P"),
+            _ => None,
+        },
+        Constellation::QZSS => match sig_id {
+            0 => Some("1C"),
+            1 => Some("1Z"),
+            4 => Some("2S"),
+            5 => Some("2L"),
+            8 => Some("5I"),
+            9 => Some("5Q"),
+            _ => None,
+        },
+        Constellation::Glonass => match sig_id {
+            0 => Some("1C"),
+            2 => Some("2C"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns carrier frequency [Hz] of said RINEX signal code (like "2L", or "2" in V2).
+/// The Glonass frequency channel is only used for Glonass signals.
+pub fn carrier_frequency(constellation: Constellation, code: &str, glo_channel: i8) -> Option<f64> {
+    let band = code.chars().next()?;
+    let k = glo_channel as f64;
+
+    match (constellation, band) {
+        (Constellation::GPS | Constellation::QZSS | Constellation::SBAS, '1') => Some(1575.42E6),
+        (Constellation::GPS | Constellation::QZSS, '2') => Some(1227.60E6),
+        (Constellation::GPS | Constellation::QZSS | Constellation::SBAS, '5') => Some(1176.45E6),
+        (Constellation::Galileo, '1') => Some(1575.42E6),
+        (Constellation::Galileo, '5') => Some(1176.45E6),
+        (Constellation::Galileo, '6') => Some(1278.75E6),
+        (Constellation::Galileo, '7') => Some(1207.14E6),
+        (Constellation::BeiDou, '1') => Some(1575.42E6),
+        (Constellation::BeiDou, '2') => Some(1561.098E6),
+        (Constellation::BeiDou, '5') => Some(1176.45E6),
+        (Constellation::BeiDou, '7') => Some(1207.14E6),
+        (Constellation::Glonass, '1') => Some(1602.0E6 + k * 0.5625E6),
+        (Constellation::Glonass, '2') => Some(1246.0E6 + k * 0.4375E6),
+        _ => None,
+    }
+}
+
 // pub fn freq_id_to_carrier(constellation: Constellation, freq_id: u8) -> Option<Carrier> {
 //     match constellation {
 //         Constellation::GPS => match freq_id {
@@ -82,3 +214,24 @@ pub fn to_constellation(id: u8) -> Option<Constellation> {
 //         _ => None,
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use super::geodetic_to_ecef;
+
+    #[test]
+    fn test_geodetic_to_ecef() {
+        for ((lat, long, alt), (x, y, z)) in [
+            ((0.0, 0.0, 0.0), (6_378_137.0, 0.0, 0.0)),
+            ((0.0, 90.0, 0.0), (0.0, 6_378_137.0, 0.0)),
+            ((0.0, 0.0, 100.0), (6_378_237.0, 0.0, 0.0)),
+            ((90.0, 0.0, 0.0), (0.0, 0.0, 6_356_752.314_245)),
+        ] {
+            let ecef = geodetic_to_ecef(lat, long, alt);
+
+            assert!((ecef.0 - x).abs() < 1.0E-3, "x={} expected {}", ecef.0, x);
+            assert!((ecef.1 - y).abs() < 1.0E-3, "y={} expected {}", ecef.1, y);
+            assert!((ecef.2 - z).abs() < 1.0E-3, "z={} expected {}", ecef.2, z);
+        }
+    }
+}